@@ -0,0 +1,155 @@
+use crate::{AsColumnFamilyRef, DBWithThreadMode, Error, ThreadMode, WriteBatch, WriteOptions};
+
+use std::mem;
+
+/// Streams an unbounded number of writes into a database with bounded memory usage.
+///
+/// Writes are buffered in a [`WriteBatch`] which is written to the database as soon as it
+/// reaches `max_bytes` serialized bytes or `max_ops` operations. Each of those writes is atomic,
+/// but the stream as a whole is not: a failure part way through leaves the batches written so
+/// far in the database.
+///
+/// Buffered writes which were not written yet are discarded when the writer is dropped, so
+/// [`finish`] must be called once all writes have been added.
+///
+/// ```
+/// use rocksdb::{BatchWriter, Options, DB};
+///
+/// let path = "_path_for_rocksdb_storage_batch_writer";
+/// {
+///     let db = DB::open_default(path).unwrap();
+///     let mut writer = BatchWriter::new(&db, 4 << 20, 10_000);
+///     for i in 0..100_000_u32 {
+///         writer.put(i.to_be_bytes(), b"value").unwrap();
+///     }
+///     writer.finish().unwrap();
+/// }
+/// let _ = DB::destroy(&Options::default(), path);
+/// ```
+///
+/// [`finish`]: Self::finish
+pub struct BatchWriter<'a, T: ThreadMode> {
+    db: &'a DBWithThreadMode<T>,
+    batch: WriteBatch,
+    writeopts: WriteOptions,
+    max_bytes: usize,
+    max_ops: usize,
+}
+
+impl<'a, T: ThreadMode> BatchWriter<'a, T> {
+    /// Creates a writer which writes its batch to `db` using default write options whenever it
+    /// holds `max_bytes` serialized bytes or `max_ops` operations.
+    pub fn new(db: &'a DBWithThreadMode<T>, max_bytes: usize, max_ops: usize) -> Self {
+        Self::new_opt(db, max_bytes, max_ops, WriteOptions::default())
+    }
+
+    /// Creates a writer which writes its batch to `db` using the given write options whenever
+    /// it holds `max_bytes` serialized bytes or `max_ops` operations.
+    pub fn new_opt(
+        db: &'a DBWithThreadMode<T>,
+        max_bytes: usize,
+        max_ops: usize,
+        writeopts: WriteOptions,
+    ) -> Self {
+        Self {
+            db,
+            batch: WriteBatch::default(),
+            writeopts,
+            max_bytes,
+            max_ops,
+        }
+    }
+
+    /// Adds a put and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn put<K, V>(&mut self, key: K, value: V) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.batch.put(key, value);
+        self.flush_if_full()
+    }
+
+    /// Adds a put to a column family and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn put_cf<K, V>(
+        &mut self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        value: V,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.batch.put_cf(cf, key, value);
+        self.flush_if_full()
+    }
+
+    /// Adds a merge and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn merge<K, V>(&mut self, key: K, value: V) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.batch.merge(key, value);
+        self.flush_if_full()
+    }
+
+    /// Adds a merge to a column family and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn merge_cf<K, V>(
+        &mut self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        value: V,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.batch.merge_cf(cf, key, value);
+        self.flush_if_full()
+    }
+
+    /// Adds a delete and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), Error> {
+        self.batch.delete(key);
+        self.flush_if_full()
+    }
+
+    /// Adds a delete to a column family and writes the batch if it reached `max_ops` or `max_bytes`.
+    pub fn delete_cf<K: AsRef<[u8]>>(
+        &mut self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+    ) -> Result<(), Error> {
+        self.batch.delete_cf(cf, key);
+        self.flush_if_full()
+    }
+
+    /// Writes the buffered operations to the database, if there are any.
+    ///
+    /// Fails with a [`ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped) error if a
+    /// write was added for a column family which was dropped at that point, even if that left
+    /// nothing to write.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        let batch = mem::take(&mut self.batch);
+        if batch.is_empty() {
+            // Writes to dropped column families are left out of the batch.
+            return batch.check_cf_not_dropped();
+        }
+        self.db.write_opt(batch, &self.writeopts)
+    }
+
+    /// Writes the remaining buffered operations to the database and consumes the writer.
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.flush()
+    }
+
+    fn flush_if_full(&mut self) -> Result<(), Error> {
+        if self.batch.len() >= self.max_ops || self.batch.size_in_bytes() >= self.max_bytes {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{db::MultiThreaded, ffi, Options};
+use crate::{comparator::CompareFn, db::MultiThreaded, ffi, Error, Options};
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 /// The name of the default column family.
 ///
@@ -41,21 +44,37 @@ impl ColumnFamilyDescriptor {
             options,
         }
     }
+
+    /// Returns the name of the column family.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the options the column family is opened with.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
 }
 
 /// An opaque type used to represent a column family. Returned from some functions, and used
 /// in others
 pub struct ColumnFamily {
     pub(crate) inner: *mut ffi::rocksdb_column_family_handle_t,
+    pub(crate) comparator: Option<CompareFn>,
 }
 
 /// A specialized opaque type used to represent a column family by the [`MultiThreaded`]
 /// mode. Clone (and Copy) is derived to behave like `&ColumnFamily` (this is used for
 /// single-threaded mode). `Clone`/`Copy` is safe because this lifetime is bound to DB like
-/// iterators/snapshots. On top of it, this is as cheap and small as `&ColumnFamily` because
-/// this only has a single pointer-wide field.
+/// iterators/snapshots. On top of it, this is nearly as cheap and small as `&ColumnFamily`
+/// because this only has a pointer-wide field, the comparator of the column family and a flag
+/// recording whether the column family was dropped with `drop_cf` in the meantime.
+// `repr(C)` keeps the layout identical to `UnboundColumnFamily`, see `bound_column_family()`.
+#[repr(C)]
 pub struct BoundColumnFamily<'a> {
     pub(crate) inner: *mut ffi::rocksdb_column_family_handle_t,
+    pub(crate) comparator: Option<CompareFn>,
+    pub(crate) dropped: AtomicBool,
     pub(crate) multi_threaded_cfs: std::marker::PhantomData<&'a MultiThreaded>,
 }
 
@@ -63,11 +82,31 @@ pub struct BoundColumnFamily<'a> {
 // but its memory will be exposed after transmute()-ing to BoundColumnFamily.
 // ColumnFamily's lifetime should be bound to DB. But, db holds cfs and cfs can't easily
 // self-reference DB as its lifetime due to rust's type system
+#[repr(C)]
 pub(crate) struct UnboundColumnFamily {
     pub(crate) inner: *mut ffi::rocksdb_column_family_handle_t,
+    pub(crate) comparator: Option<CompareFn>,
+    pub(crate) dropped: AtomicBool,
 }
 
 impl UnboundColumnFamily {
+    pub(crate) fn new(
+        inner: *mut ffi::rocksdb_column_family_handle_t,
+        comparator: Option<CompareFn>,
+    ) -> Self {
+        Self {
+            inner,
+            comparator,
+            dropped: AtomicBool::new(false),
+        }
+    }
+
+    /// Marks the column family as dropped, so that operations through handles which are still
+    /// held fail with [`ErrorKind::ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped).
+    pub(crate) fn mark_dropped(&self) {
+        self.dropped.store(true, Ordering::Release);
+    }
+
     pub(crate) fn bound_column_family<'a>(self: Arc<Self>) -> Arc<BoundColumnFamily<'a>> {
         // SAFETY: the new BoundColumnFamily here just adding lifetime,
         // so that column family handle won't outlive db.
@@ -115,18 +154,40 @@ pub type ColumnFamilyRef<'a> = Arc<BoundColumnFamily<'a>>;
 /// (`&ColumnFamily` and `BoundColumnFamily`)
 pub trait AsColumnFamilyRef {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t;
+
+    /// Whether the column family was dropped while this handle was still held.
+    ///
+    /// Only handles of the [`MultiThreaded`] mode can outlive `drop_cf`; in single-threaded
+    /// mode the borrow checker already rules this out.
+    fn is_dropped(&self) -> bool {
+        false
+    }
+
+    /// The comparator set with [`Options::set_comparator`] on the options the column family
+    /// was opened or created with, or `None` if it orders keys bytewise.
+    fn comparator(&self) -> Option<CompareFn> {
+        None
+    }
 }
 
 impl AsColumnFamilyRef for ColumnFamily {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
         self.inner
     }
+
+    fn comparator(&self) -> Option<CompareFn> {
+        self.comparator
+    }
 }
 
 impl<'a> AsColumnFamilyRef for &'a ColumnFamily {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
         self.inner
     }
+
+    fn comparator(&self) -> Option<CompareFn> {
+        self.comparator
+    }
 }
 
 // Only implement for Arc-ed BoundColumnFamily as this tightly coupled and
@@ -138,6 +199,56 @@ impl<'a> AsColumnFamilyRef for Arc<BoundColumnFamily<'a>> {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
         self.inner
     }
+
+    fn is_dropped(&self) -> bool {
+        self.dropped.load(Ordering::Acquire)
+    }
+
+    fn comparator(&self) -> Option<CompareFn> {
+        self.comparator
+    }
+}
+
+/// Fails with a [`ErrorKind::ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped)
+/// error if the column family behind `cf` was dropped.
+pub(crate) fn check_not_dropped(cf: &impl AsColumnFamilyRef) -> Result<(), Error> {
+    if cf.is_dropped() {
+        Err(column_family_dropped())
+    } else {
+        Ok(())
+    }
+}
+
+/// The error reported by operations on a column family which was dropped.
+pub(crate) fn column_family_dropped() -> Error {
+    Error::new("Column family dropped".to_owned())
+}
+
+/// Fails with an `InvalidArgument` error if `from` sorts after `to` according to `comparator`,
+/// or bytewise if there is none.
+///
+/// RocksDB only checks the bounds of a range deletion once the batch holding it has been
+/// written to the WAL, and then fails all later writes as well, so the bounds are checked
+/// here before anything is written.
+pub(crate) fn check_range(
+    comparator: Option<CompareFn>,
+    from: &[u8],
+    to: &[u8],
+) -> Result<(), Error> {
+    let inverted = match comparator {
+        Some(compare) => compare(from, to) == std::cmp::Ordering::Greater,
+        None => from > to,
+    };
+    if inverted {
+        Err(inverted_range())
+    } else {
+        Ok(())
+    }
+}
+
+/// The error reported for a range deletion whose start key sorts after its end key.
+pub(crate) fn inverted_range() -> Error {
+    Error::new("Invalid argument: start key of range is greater than end key".to_owned())
 }
 
 unsafe impl Send for ColumnFamily {}
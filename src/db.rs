@@ -17,10 +17,13 @@ use crate::{
     column_family::AsColumnFamilyRef,
     column_family::BoundColumnFamily,
     column_family::UnboundColumnFamily,
+    column_family::{check_not_dropped, check_range, column_family_dropped},
+    comparator::CompareFn,
+    db_lock::{self, ProcessLock},
     db_options::OptionsMustOutliveDB,
     ffi,
     ffi_util::{from_cstr, opt_bytes_to_ptr, raw_data, to_cpath, CStrLike},
-    ColumnFamily, ColumnFamilyDescriptor, CompactOptions, DBIteratorWithThreadMode,
+    properties, ColumnFamily, ColumnFamilyDescriptor, CompactOptions, DBIteratorWithThreadMode,
     DBPinnableSlice, DBRawIteratorWithThreadMode, DBWALIterator, Direction, Error, FlushOptions,
     IngestExternalFileOptions, IteratorMode, Options, ReadOptions, SnapshotWithThreadMode,
     WriteBatch, WriteOptions, DEFAULT_COLUMN_FAMILY_NAME,
@@ -52,12 +55,16 @@ use std::time::Duration;
 /// [`SingleThreaded`] and [`MultiThreaded`].  These methods aren't expected to be
 /// called and defined externally.
 pub trait ThreadMode {
-    /// Internal implementation for storing column family handles
+    /// Internal implementation for storing column family handles along with their comparators
     fn new_cf_map_internal(
-        cf_map: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
+        cf_map: BTreeMap<String, (*mut ffi::rocksdb_column_family_handle_t, Option<CompareFn>)>,
     ) -> Self;
     /// Internal implementation for dropping column family handles
     fn drop_all_cfs_internal(&mut self);
+    /// Internal implementation for listing raw handles of the non-default column families
+    fn cf_handles_internal(&self) -> Vec<*mut ffi::rocksdb_column_family_handle_t>;
+    /// Internal implementation for finding the name of a column family by its raw handle
+    fn cf_name_internal(&self, cf: *mut ffi::rocksdb_column_family_handle_t) -> Option<String>;
 }
 
 /// Actual marker type for the marker trait `ThreadMode`, which holds
@@ -81,12 +88,12 @@ pub struct MultiThreaded {
 
 impl ThreadMode for SingleThreaded {
     fn new_cf_map_internal(
-        cfs: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
+        cfs: BTreeMap<String, (*mut ffi::rocksdb_column_family_handle_t, Option<CompareFn>)>,
     ) -> Self {
         Self {
             cfs: cfs
                 .into_iter()
-                .map(|(n, c)| (n, ColumnFamily { inner: c }))
+                .map(|(n, (inner, comparator))| (n, ColumnFamily { inner, comparator }))
                 .collect(),
         }
     }
@@ -95,16 +102,33 @@ impl ThreadMode for SingleThreaded {
         // Cause all ColumnFamily objects to be Drop::drop()-ed.
         self.cfs.clear();
     }
+
+    fn cf_handles_internal(&self) -> Vec<*mut ffi::rocksdb_column_family_handle_t> {
+        self.cfs
+            .iter()
+            .filter(|(name, _)| name.as_str() != DEFAULT_COLUMN_FAMILY_NAME)
+            .map(|(_, cf)| cf.inner)
+            .collect()
+    }
+
+    fn cf_name_internal(&self, cf: *mut ffi::rocksdb_column_family_handle_t) -> Option<String> {
+        self.cfs
+            .iter()
+            .find(|(_, handle)| handle.inner == cf)
+            .map(|(name, _)| name.clone())
+    }
 }
 
 impl ThreadMode for MultiThreaded {
     fn new_cf_map_internal(
-        cfs: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
+        cfs: BTreeMap<String, (*mut ffi::rocksdb_column_family_handle_t, Option<CompareFn>)>,
     ) -> Self {
         Self {
             cfs: RwLock::new(
                 cfs.into_iter()
-                    .map(|(n, c)| (n, Arc::new(UnboundColumnFamily { inner: c })))
+                    .map(|(n, (inner, comparator))| {
+                        (n, Arc::new(UnboundColumnFamily::new(inner, comparator)))
+                    })
                     .collect(),
             ),
         }
@@ -114,6 +138,39 @@ impl ThreadMode for MultiThreaded {
         // Cause all UnboundColumnFamily objects to be Drop::drop()-ed.
         self.cfs.write().unwrap().clear();
     }
+
+    fn cf_handles_internal(&self) -> Vec<*mut ffi::rocksdb_column_family_handle_t> {
+        self.cfs
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| name.as_str() != DEFAULT_COLUMN_FAMILY_NAME)
+            .map(|(_, cf)| cf.inner)
+            .collect()
+    }
+
+    fn cf_name_internal(&self, cf: *mut ffi::rocksdb_column_family_handle_t) -> Option<String> {
+        self.cfs
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, handle)| handle.inner == cf)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// The comparator the default column family is opened with: the one of its descriptor if there
+/// is one, otherwise the one of `opts` if no column families are given at all.
+pub(crate) fn default_cf_comparator(
+    opts: &Options,
+    cfs: &[ColumnFamilyDescriptor],
+) -> Option<CompareFn> {
+    if cfs.is_empty() {
+        return opts.comparator;
+    }
+    cfs.iter()
+        .find(|cf| cf.name == DEFAULT_COLUMN_FAMILY_NAME)
+        .and_then(|cf| cf.options.comparator)
 }
 
 /// Get underlying `rocksdb_t`.
@@ -130,6 +187,10 @@ pub struct DBCommon<T: ThreadMode, D: DBInner> {
     cfs: T, // Column families are held differently depending on thread mode
     path: PathBuf,
     _outlive: Vec<OptionsMustOutliveDB>,
+    // The comparator of the default column family, see `Options::set_comparator`.
+    default_comparator: Option<CompareFn>,
+    // Dropped last, once the database has been closed.
+    _lock: Option<ProcessLock>,
 }
 
 /// Minimal set of DB-related methods, intended to be generic over
@@ -335,12 +396,129 @@ unsafe impl<T: ThreadMode + Send, I: DBInner> Send for DBCommon<T, I> {}
 // use within the rocksdb library is generally behind a const reference
 unsafe impl<T: ThreadMode, I: DBInner> Sync for DBCommon<T, I> {}
 
+// Time to Live of each column family, keyed by name.
+type TTLMap = BTreeMap<String, Duration>;
+
 // Specifies whether open DB for read only.
 enum AccessType<'a> {
     ReadWrite,
     ReadOnly { error_if_log_file_exist: bool },
     Secondary { secondary_path: &'a Path },
     WithTTL { ttl: Duration },
+    WithTTLPerCF { ttls: &'a TTLMap },
+}
+
+impl AccessType<'_> {
+    /// Whether opening the database with this access type takes its LOCK file.
+    fn takes_lock(&self) -> bool {
+        !matches!(self, Self::ReadOnly { .. } | Self::Secondary { .. })
+    }
+}
+
+/// Unified entry point for opening a database, which resolves the combination of access mode,
+/// column family descriptors and TTLs to the matching `DBWithThreadMode::open_*` call.
+///
+/// ```
+/// use rocksdb::{ColumnFamilyDescriptor, OpenBuilder, Options, DB};
+///
+/// let path = "_path_for_open_builder";
+/// {
+///     let mut opts = Options::default();
+///     opts.create_if_missing(true);
+///     opts.create_missing_column_families(true);
+///     let cf = ColumnFamilyDescriptor::new("cf1", Options::default());
+///     let db: DB = OpenBuilder::new(opts).descriptors(vec![cf]).open(path).unwrap();
+///     assert!(db.cf_handle("cf1").is_some());
+/// }
+/// let _ = DB::destroy(&Options::default(), path);
+/// ```
+pub struct OpenBuilder {
+    opts: Options,
+    read_only: bool,
+    error_if_log_file_exist: bool,
+    secondary_path: Option<PathBuf>,
+    ttls: Option<TTLMap>,
+    cfs: Vec<ColumnFamilyDescriptor>,
+}
+
+impl OpenBuilder {
+    /// Creates a builder which opens the database read-write with the given options.
+    pub fn new(opts: Options) -> Self {
+        Self {
+            opts,
+            read_only: false,
+            error_if_log_file_exist: false,
+            secondary_path: None,
+            ttls: None,
+            cfs: Vec::new(),
+        }
+    }
+
+    /// Opens the database for read only.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// When opening for read only, fail if there are WAL files which were not yet recovered.
+    pub fn error_if_log_file_exist(mut self, error_if_log_file_exist: bool) -> Self {
+        self.error_if_log_file_exist = error_if_log_file_exist;
+        self
+    }
+
+    /// Opens the database as a secondary instance, keeping its own info logs and metadata at
+    /// `secondary_path`.
+    pub fn secondary<P: AsRef<Path>>(mut self, secondary_path: P) -> Self {
+        self.secondary_path = Some(secondary_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Opens the database with a Time to Live compaction filter, using a separate TTL for each
+    /// column family. Column families missing from the map get no TTL.
+    pub fn ttl_per_cf<I, N>(mut self, ttls: I) -> Self
+    where
+        I: IntoIterator<Item = (N, Duration)>,
+        N: AsRef<str>,
+    {
+        self.ttls = Some(
+            ttls.into_iter()
+                .map(|(name, ttl)| (name.as_ref().to_owned(), ttl))
+                .collect(),
+        );
+        self
+    }
+
+    /// Sets the column families to open.
+    pub fn descriptors<I>(mut self, cfs: I) -> Self
+    where
+        I: IntoIterator<Item = ColumnFamilyDescriptor>,
+    {
+        self.cfs = cfs.into_iter().collect();
+        self
+    }
+
+    /// Opens the database at `path`.
+    ///
+    /// Read only, secondary and TTL modes are mutually exclusive; combining them is an error.
+    pub fn open<T: ThreadMode, P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<DBWithThreadMode<T>, Error> {
+        let access_type = match (self.read_only, &self.secondary_path, &self.ttls) {
+            (false, None, None) => AccessType::ReadWrite,
+            (true, None, None) => AccessType::ReadOnly {
+                error_if_log_file_exist: self.error_if_log_file_exist,
+            },
+            (false, Some(secondary_path), None) => AccessType::Secondary { secondary_path },
+            (false, None, Some(ttls)) => AccessType::WithTTLPerCF { ttls },
+            _ => {
+                return Err(Error::new(
+                    "Read only, secondary and TTL modes cannot be combined.".to_owned(),
+                ))
+            }
+        };
+        DBWithThreadMode::open_cf_descriptors_internal(&self.opts, path, self.cfs, &access_type)
+    }
 }
 
 /// Methods of `DBWithThreadMode`.
@@ -567,6 +745,25 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         Self::open_cf_descriptors_internal(opts, path, cfs, &AccessType::ReadWrite)
     }
 
+    /// Opens a database with the given database options and column family descriptors,
+    /// creating the column families which don't exist yet with their descriptor's options.
+    ///
+    /// This is a shorthand for `open_cf_descriptors` with `create_missing_column_families`
+    /// enabled on a copy of `opts`.
+    pub fn open_cf_descriptors_create_missing<P, I>(
+        opts: &Options,
+        path: P,
+        cfs: I,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = ColumnFamilyDescriptor>,
+    {
+        let mut opts = opts.clone();
+        opts.create_missing_column_families(true);
+        Self::open_cf_descriptors_internal(&opts, path, cfs, &AccessType::ReadWrite)
+    }
+
     /// Internal implementation for opening RocksDB.
     fn open_cf_descriptors_internal<P, I>(
         opts: &Options,
@@ -582,6 +779,7 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         let outlive = iter::once(opts.outlive.clone())
             .chain(cfs.iter().map(|cf| cf.options.outlive.clone()))
             .collect();
+        let default_comparator = default_cf_comparator(opts, &cfs);
 
         let cpath = to_cpath(&path)?;
 
@@ -591,6 +789,11 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
                 e
             )));
         }
+        let lock = if access_type.takes_lock() {
+            Some(ProcessLock::acquire(path.as_ref()))
+        } else {
+            None
+        };
 
         let db: *mut ffi::rocksdb_t;
         let mut cf_map = BTreeMap::new();
@@ -641,7 +844,7 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
             }
 
             for (cf_desc, inner) in cfs_v.iter().zip(cfhandles) {
-                cf_map.insert(cf_desc.name.clone(), inner);
+                cf_map.insert(cf_desc.name.clone(), (inner, cf_desc.options.comparator));
             }
         }
 
@@ -654,6 +857,8 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
             path: path.as_ref().to_path_buf(),
             cfs: T::new_cf_map_internal(cf_map),
             _outlive: outlive,
+            default_comparator,
+            _lock: lock,
         })
     }
 
@@ -686,6 +891,12 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
                     cpath.as_ptr(),
                     ttl.as_secs() as c_int,
                 )),
+                AccessType::WithTTLPerCF { ttls } => {
+                    let ttl = ttls
+                        .get(DEFAULT_COLUMN_FAMILY_NAME)
+                        .map_or(0, |ttl| ttl.as_secs() as c_int);
+                    ffi_try!(ffi::rocksdb_open_with_ttl(opts.inner, cpath.as_ptr(), ttl))
+                }
             }
         };
         Ok(db)
@@ -745,12 +956,30 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
                         ttls_v.as_ptr(),
                     ))
                 }
+                AccessType::WithTTLPerCF { ttls } => {
+                    let ttls_v: Vec<_> = cfs_v
+                        .iter()
+                        .map(|cf| ttls.get(&cf.name).map_or(0, |ttl| ttl.as_secs() as c_int))
+                        .collect();
+                    ffi_try!(ffi::rocksdb_open_column_families_with_ttl(
+                        opts.inner,
+                        cpath.as_ptr(),
+                        cfs_v.len() as c_int,
+                        cfnames.as_ptr(),
+                        cfopts.as_ptr(),
+                        cfhandles.as_mut_ptr(),
+                        ttls_v.as_ptr(),
+                    ))
+                }
             }
         };
         Ok(db)
     }
 
     /// Removes the database entries in the range `["from", "to")` using given write options.
+    ///
+    /// Returns an `InvalidArgument` error if `from` sorts after `to` according to the comparator
+    /// of the column family. An empty range, i.e. `from == to`, removes nothing.
     pub fn delete_range_cf_opt<K: AsRef<[u8]>>(
         &self,
         cf: &impl AsColumnFamilyRef,
@@ -758,9 +987,10 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         to: K,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         let from = from.as_ref();
         let to = to.as_ref();
-
+        check_range(cf.comparator(), from, to)?;
         unsafe {
             ffi_try!(ffi::rocksdb_delete_range_cf(
                 self.inner.inner(),
@@ -785,7 +1015,14 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         self.delete_range_cf_opt(cf, from, to, &WriteOptions::default())
     }
 
+    /// Atomically applies the operations of `batch` using given write options.
+    ///
+    /// Fails without writing anything if an operation was added for a column family which was
+    /// dropped at that point, or if the batch holds a range deletion whose start key sorts after
+    /// its end key.
     pub fn write_opt(&self, batch: WriteBatch, writeopts: &WriteOptions) -> Result<(), Error> {
+        batch.check_cf_not_dropped()?;
+        batch.check_ranges(self.default_comparator)?;
         unsafe {
             ffi_try!(ffi::rocksdb_write(
                 self.inner.inner(),
@@ -809,12 +1046,21 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
 
 /// Common methods of `DBWithThreadMode` and `OptimisticTransactionDB`.
 impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
-    pub(crate) fn new(inner: D, cfs: T, path: PathBuf, outlive: Vec<OptionsMustOutliveDB>) -> Self {
+    pub(crate) fn new(
+        inner: D,
+        cfs: T,
+        path: PathBuf,
+        outlive: Vec<OptionsMustOutliveDB>,
+        default_comparator: Option<CompareFn>,
+        lock: Option<ProcessLock>,
+    ) -> Self {
         Self {
             inner,
             cfs,
             path,
             _outlive: outlive,
+            default_comparator,
+            _lock: lock,
         }
     }
 
@@ -854,6 +1100,17 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
+    /// Checks whether the database at `path` is currently held open by a writer, either in
+    /// this process or in another one.
+    ///
+    /// This only inspects the OS advisory lock RocksDB holds on the `LOCK` file of the
+    /// database; the database itself is never opened, and nothing is created under `path`.
+    /// Returns `false` when `path` doesn't hold a `LOCK` file.
+    pub fn is_locked<P: AsRef<Path>>(path: P) -> bool {
+        db_lock::is_locked(path.as_ref())
+    }
+
+    /// Returns the path the database was opened at, as it was passed to the open call.
     pub fn path(&self) -> &Path {
         self.path.as_path()
     }
@@ -889,6 +1146,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf: &impl AsColumnFamilyRef,
         flushopts: &FlushOptions,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_flush_cf(
                 self.inner.inner(),
@@ -995,6 +1253,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         key: K,
         readopts: &ReadOptions,
     ) -> Result<Option<DBPinnableSlice>, Error> {
+        check_not_dropped(cf)?;
         if readopts.inner.is_null() {
             return Err(Error::new(
                 "Unable to create RocksDB read options. This is a fairly trivial call, and its \
@@ -1075,7 +1334,34 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         convert_values(values, values_sizes, errors)
     }
 
+    /// Return the values associated with the given keys, or the first error encountered if
+    /// any of the lookups failed.
+    pub fn multi_get_ok_or_first_err<K, I>(&self, keys: I) -> Result<Vec<Option<Vec<u8>>>, Error>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        self.multi_get_ok_or_first_err_opt(keys, &ReadOptions::default())
+    }
+
+    /// Return the values associated with the given keys using read options, or the first error
+    /// encountered if any of the lookups failed.
+    pub fn multi_get_ok_or_first_err_opt<K, I>(
+        &self,
+        keys: I,
+        readopts: &ReadOptions,
+    ) -> Result<Vec<Option<Vec<u8>>>, Error>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        self.multi_get_opt(keys, readopts).into_iter().collect()
+    }
+
     /// Return the values associated with the given keys and column families.
+    ///
+    /// Each key is read from the column family it's paired with, all in a single batch. The
+    /// results are in the order of `keys`.
     pub fn multi_get_cf<'a, 'b: 'a, K, I, W>(
         &'a self,
         keys: I,
@@ -1089,6 +1375,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     }
 
     /// Return the values associated with the given keys and column families using read options.
+    ///
+    /// If any of the column families was dropped, every result is a
+    /// [`ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped) error.
     pub fn multi_get_cf_opt<'a, 'b: 'a, K, I, W>(
         &'a self,
         keys: I,
@@ -1103,6 +1392,12 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
             .into_iter()
             .map(|(cf, key)| ((cf, Box::from(key.as_ref())), key.as_ref().len()))
             .unzip();
+        if cfs_and_keys.iter().any(|(cf, _)| cf.is_dropped()) {
+            return cfs_and_keys
+                .iter()
+                .map(|_| Err(column_family_dropped()))
+                .collect();
+        }
         let ptr_keys: Vec<_> = cfs_and_keys
             .iter()
             .map(|(_, k)| k.as_ptr() as *const c_char)
@@ -1135,6 +1430,11 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     /// Return the values associated with the given keys and the specified column family
     /// where internally the read requests are processed in batch if block-based table
     /// SST format is used.  It is a more optimized version of multi_get_cf.
+    ///
+    /// The values are returned as [`DBPinnableSlice`]s, in the order of `keys`, without being
+    /// copied. If `sorted_input` is true, `keys` must already be sorted by the comparator of
+    /// the column family, so that RocksDB can skip sorting them. Passing unsorted keys with
+    /// `sorted_input` set gives undefined results.
     pub fn batched_multi_get_cf<K, I>(
         &self,
         cf: &impl AsColumnFamilyRef,
@@ -1151,6 +1451,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     /// Return the values associated with the given keys and the specified column family
     /// where internally the read requests are processed in batch if block-based table
     /// SST format is used.  It is a more optimized version of multi_get_cf_opt.
+    ///
+    /// See [`batched_multi_get_cf`](Self::batched_multi_get_cf) for the meaning of
+    /// `sorted_input`.
     pub fn batched_multi_get_cf_opt<K, I>(
         &self,
         cf: &impl AsColumnFamilyRef,
@@ -1166,6 +1469,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
             .into_iter()
             .map(|k| (Box::from(k.as_ref()), k.as_ref().len()))
             .unzip();
+        if cf.is_dropped() {
+            return keys.iter().map(|_| Err(column_family_dropped())).collect();
+        }
         let ptr_keys: Vec<_> = keys.iter().map(|k| k.as_ptr() as *const c_char).collect();
 
         let mut pinned_values = vec![ptr::null_mut(); ptr_keys.len()];
@@ -1201,6 +1507,32 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Return the values associated with the keys formed by appending each of `suffixes` to
+    /// `prefix` in the specified column family, using [`batched_multi_get_cf`].
+    ///
+    /// When the column family has a prefix extractor matching `prefix`, all lookups share the
+    /// same prefix bloom filter and index checks, which makes this cheaper than separate gets.
+    ///
+    /// [`batched_multi_get_cf`]: Self::batched_multi_get_cf
+    pub fn multi_get_prefix_cf<S, I>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        prefix: &[u8],
+        suffixes: I,
+    ) -> Vec<Result<Option<DBPinnableSlice>, Error>>
+    where
+        S: AsRef<[u8]>,
+        I: IntoIterator<Item = S>,
+    {
+        let keys: Vec<Vec<u8>> = suffixes
+            .into_iter()
+            .map(|suffix| [prefix, suffix.as_ref()].concat())
+            .collect();
+        // The keys may be sorted bytewise but not by the comparator of the column family, so let
+        // RocksDB sort them.
+        self.batched_multi_get_cf(cf, keys, false)
+    }
+
     /// Returns `false` if the given key definitely doesn't exist in the database, otherwise returns
     /// `true`. This function uses default `ReadOptions`.
     pub fn key_may_exist<K: AsRef<[u8]>>(&self, key: K) -> bool {
@@ -1233,13 +1565,16 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     }
 
     /// Returns `false` if the given key definitely doesn't exist in the specified column family,
-    /// otherwise returns `true`.
+    /// otherwise returns `true`. Always returns `false` once the column family was dropped.
     pub fn key_may_exist_cf_opt<K: AsRef<[u8]>>(
         &self,
         cf: &impl AsColumnFamilyRef,
         key: K,
         readopts: &ReadOptions,
     ) -> bool {
+        if cf.is_dropped() {
+            return false;
+        }
         let key = key.as_ref();
         0 != unsafe {
             ffi::rocksdb_key_may_exist_cf(
@@ -1257,6 +1592,70 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Same as `key_may_exist_opt`, but also returns the value of the key if it could be read
+    /// without I/O, i.e. from the memtables or the block cache.
+    ///
+    /// Returns `(false, None)` if the key definitely doesn't exist, `(true, Some(value))` if its
+    /// value was found, and `(true, None)` if it may exist.
+    pub fn key_may_exist_opt_value<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        readopts: &ReadOptions,
+    ) -> (bool, Option<Vec<u8>>) {
+        let key = key.as_ref();
+        let mut val: *mut c_char = ptr::null_mut();
+        let mut val_len: size_t = 0;
+        let mut value_found: c_uchar = 0;
+        unsafe {
+            let may_exist = 0
+                != ffi::rocksdb_key_may_exist(
+                    self.inner.inner(),
+                    readopts.inner,
+                    key.as_ptr() as *const c_char,
+                    key.len() as size_t,
+                    &mut val,
+                    &mut val_len,
+                    ptr::null(), /*timestamp*/
+                    0,           /*timestamp_len*/
+                    &mut value_found,
+                );
+            (may_exist, take_found_value(val, val_len, value_found))
+        }
+    }
+
+    /// Same as `key_may_exist_cf_opt`, but also returns the value of the key if it could be
+    /// read without I/O. See [`key_may_exist_opt_value`](Self::key_may_exist_opt_value).
+    pub fn key_may_exist_cf_opt_value<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        readopts: &ReadOptions,
+    ) -> (bool, Option<Vec<u8>>) {
+        if cf.is_dropped() {
+            return (false, None);
+        }
+        let key = key.as_ref();
+        let mut val: *mut c_char = ptr::null_mut();
+        let mut val_len: size_t = 0;
+        let mut value_found: c_uchar = 0;
+        unsafe {
+            let may_exist = 0
+                != ffi::rocksdb_key_may_exist_cf(
+                    self.inner.inner(),
+                    readopts.inner,
+                    cf.inner(),
+                    key.as_ptr() as *const c_char,
+                    key.len() as size_t,
+                    &mut val,
+                    &mut val_len,
+                    ptr::null(), /*timestamp*/
+                    0,           /*timestamp_len*/
+                    &mut value_found,
+                );
+            (may_exist, take_found_value(val, val_len, value_found))
+        }
+    }
+
     fn create_inner_cf_handle(
         &self,
         name: impl CStrLike,
@@ -1300,7 +1699,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -1334,7 +1733,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -1344,7 +1743,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -1356,12 +1755,34 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
     }
 
+    /// Opens an iterator over the `[start, end)` range of the given column family which yields
+    /// keys in descending order, i.e. starting from the last key before `end`.
+    ///
+    /// `end` does not need to exist in the column family; iteration starts from the greatest
+    /// key that is strictly less than it.
+    pub fn range_iterator_rev_cf<'a: 'b, 'b, K: AsRef<[u8]>>(
+        &'a self,
+        cf_handle: &impl AsColumnFamilyRef,
+        start: K,
+        end: K,
+    ) -> DBIteratorWithThreadMode<'b, Self> {
+        let (start, end) = (start.as_ref(), end.as_ref());
+        let mut opts = ReadOptions::default();
+        opts.set_iterate_range(start..end);
+        DBIteratorWithThreadMode::new_cf(
+            self,
+            cf_handle,
+            opts,
+            IteratorMode::From(end, Direction::Reverse),
+        )
+    }
+
     /// Opens a raw iterator over the database, using the default read options
     pub fn raw_iterator<'a: 'b, 'b>(&'a self) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
@@ -1374,7 +1795,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -1391,7 +1812,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
     }
 
     pub fn snapshot(&self) -> SnapshotWithThreadMode<Self> {
@@ -1430,6 +1851,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        check_not_dropped(cf)?;
         let key = key.as_ref();
         let value = value.as_ref();
 
@@ -1479,6 +1901,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        check_not_dropped(cf)?;
         let key = key.as_ref();
         let value = value.as_ref();
 
@@ -1520,6 +1943,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         key: K,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         let key = key.as_ref();
 
         unsafe {
@@ -1618,12 +2042,17 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
 
     /// Runs a manual compaction on the Range of keys given on the
     /// given column family. This is not likely to be needed for typical usage.
+    ///
+    /// Does nothing if the column family was dropped.
     pub fn compact_range_cf<S: AsRef<[u8]>, E: AsRef<[u8]>>(
         &self,
         cf: &impl AsColumnFamilyRef,
         start: Option<S>,
         end: Option<E>,
     ) {
+        if cf.is_dropped() {
+            return;
+        }
         unsafe {
             let start = start.as_ref().map(AsRef::as_ref);
             let end = end.as_ref().map(AsRef::as_ref);
@@ -1647,6 +2076,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         end: Option<E>,
         opts: &CompactOptions,
     ) {
+        if cf.is_dropped() {
+            return;
+        }
         unsafe {
             let start = start.as_ref().map(AsRef::as_ref);
             let end = end.as_ref().map(AsRef::as_ref);
@@ -1663,6 +2095,21 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Flushes the memtable of a column family, then compacts the whole column family and
+    /// moves the result to `level`.
+    ///
+    /// This is meant for controlled shaping of the LSM tree, e.g. during a migration: all
+    /// files of the column family end up at `level`, not only the flushed ones. Automatic
+    /// compactions may move them again afterwards.
+    pub fn flush_to_level_cf(&self, cf: &impl AsColumnFamilyRef, level: i32) -> Result<(), Error> {
+        self.flush_cf(cf)?;
+        let mut opts = CompactOptions::default();
+        opts.set_change_level(true);
+        opts.set_target_level(level);
+        self.compact_range_cf_opt(cf, None::<&[u8]>, None::<&[u8]>, &opts);
+        Ok(())
+    }
+
     pub fn set_options(&self, opts: &[(&str, &str)]) -> Result<(), Error> {
         let copts = convert_options(opts)?;
         let cnames: Vec<*const c_char> = copts.iter().map(|opt| opt.0.as_ptr()).collect();
@@ -1684,6 +2131,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf: &impl AsColumnFamilyRef,
         opts: &[(&str, &str)],
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         let copts = convert_options(opts)?;
         let cnames: Vec<*const c_char> = copts.iter().map(|opt| opt.0.as_ptr()).collect();
         let cvalues: Vec<*const c_char> = copts.iter().map(|opt| opt.1.as_ptr()).collect();
@@ -1759,6 +2207,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf: &impl AsColumnFamilyRef,
         name: impl CStrLike,
     ) -> Result<Option<String>, Error> {
+        check_not_dropped(cf)?;
         Self::property_value_impl(
             name,
             |prop_name| unsafe {
@@ -1798,6 +2247,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf: &impl AsColumnFamilyRef,
         name: impl CStrLike,
     ) -> Result<Option<u64>, Error> {
+        check_not_dropped(cf)?;
         Self::property_value_impl(
             name,
             |prop_name| unsafe {
@@ -1807,11 +2257,86 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         )
     }
 
+    /// Returns the approximate on-disk size of each of the given `[start, end)` key ranges of
+    /// the column family, in bytes.
+    ///
+    /// The returned sizes are in the same order as `ranges`. Only data which has been flushed
+    /// to SST files is taken into account, data still in the memtables is not.
+    pub fn approximate_sizes_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        ranges: &[(K, K)],
+    ) -> Result<Vec<u64>, Error> {
+        check_not_dropped(cf)?;
+        let (start_keys, start_key_lens): (Vec<_>, Vec<_>) = ranges
+            .iter()
+            .map(|(start, _)| {
+                (
+                    start.as_ref().as_ptr() as *const c_char,
+                    start.as_ref().len(),
+                )
+            })
+            .unzip();
+        let (end_keys, end_key_lens): (Vec<_>, Vec<_>) = ranges
+            .iter()
+            .map(|(_, end)| (end.as_ref().as_ptr() as *const c_char, end.as_ref().len()))
+            .unzip();
+        let mut sizes = vec![0_u64; ranges.len()];
+        unsafe {
+            ffi_try!(ffi::rocksdb_approximate_sizes_cf(
+                self.inner.inner(),
+                cf.inner(),
+                ranges.len() as c_int,
+                start_keys.as_ptr(),
+                start_key_lens.as_ptr(),
+                end_keys.as_ptr(),
+                end_key_lens.as_ptr(),
+                sizes.as_mut_ptr(),
+            ));
+        }
+        Ok(sizes)
+    }
+
+    /// Returns the number of keys in the column family.
+    ///
+    /// With `exact` set to `false` this reads the `rocksdb.estimate-num-keys` property, which is
+    /// cheap but may be off by a large margin in the presence of overwrites, deletes and merges.
+    /// With `exact` set to `true` all keys of the column family are iterated over, so the cost
+    /// grows linearly with the size of the column family.
+    pub fn count_keys_cf(&self, cf: &impl AsColumnFamilyRef, exact: bool) -> Result<u64, Error> {
+        if !exact {
+            return self
+                .property_int_value_cf(cf, properties::ESTIMATE_NUM_KEYS)?
+                .ok_or_else(|| Error::new("Could not get estimated number of keys".to_owned()));
+        }
+
+        let mut readopts = ReadOptions::default();
+        readopts.fill_cache(false);
+        let mut iter = self.raw_iterator_cf_opt(cf, readopts);
+        let mut count = 0;
+        iter.seek_to_first();
+        while iter.valid() {
+            count += 1;
+            iter.next();
+        }
+        iter.status()?;
+        Ok(count)
+    }
+
     /// The sequence number of the most recent transaction.
     pub fn latest_sequence_number(&self) -> u64 {
         unsafe { ffi::rocksdb_get_latest_sequence_number(self.inner.inner()) }
     }
 
+    /// Returns the number of the oldest WAL file which is still needed by the database.
+    ///
+    /// WAL files with lower numbers contain only data that has already been flushed to SST
+    /// files, so downstream consumers (e.g. for WAL-based replication) may trim them safely.
+    pub fn min_log_number_to_keep(&self) -> Result<u64, Error> {
+        self.property_int_value(properties::MIN_LOG_NUMBER_TO_KEEP)?
+            .ok_or_else(|| Error::new("Could not get min log number to keep".to_owned()))
+    }
+
     /// Iterate over batches of write operations since a given sequence.
     ///
     /// Produce an iterator that will provide the batches of write operations
@@ -1846,6 +2371,15 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
+    /// Tries to catch up with the primary like [`try_catch_up_with_primary`], returning the
+    /// sequence number of the last update applied to this secondary.
+    ///
+    /// [`try_catch_up_with_primary`]: Self::try_catch_up_with_primary
+    pub fn try_catch_up_with_primary_sequence(&self) -> Result<u64, Error> {
+        self.try_catch_up_with_primary()?;
+        Ok(self.latest_sequence_number())
+    }
+
     /// Loads a list of external SST files created with SstFileWriter into the DB with default opts
     pub fn ingest_external_file<P: AsRef<Path>>(&self, paths: Vec<P>) -> Result<(), Error> {
         let opts = IngestExternalFileOptions::default();
@@ -1886,6 +2420,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         opts: &IngestExternalFileOptions,
         paths: Vec<P>,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         let paths_v: Vec<CString> = paths
             .iter()
             .map(|path| to_cpath(&path))
@@ -1981,6 +2516,39 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Returns the numbers of files, entries and deletions of each non-empty level of the
+    /// column family, ordered by level.
+    ///
+    /// This is derived from [`live_files`](Self::live_files), as the C API doesn't expose the
+    /// column family metadata nor the table properties of its files. Entries include
+    /// deletions, and a key may be counted at several levels.
+    pub fn level_key_stats_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+    ) -> Result<Vec<LevelKeyStats>, Error> {
+        check_not_dropped(cf)?;
+        // The live files identify their column family by name.
+        let cf_name = self.cfs.cf_name_internal(cf.inner()).ok_or_else(|| {
+            Error::new("Invalid argument: column family of another database".to_owned())
+        })?;
+        let mut levels = BTreeMap::new();
+        for file in self.live_files()? {
+            if file.column_family_name != cf_name {
+                continue;
+            }
+            let stats = levels.entry(file.level).or_insert(LevelKeyStats {
+                level: file.level,
+                num_files: 0,
+                num_entries: 0,
+                num_deletions: 0,
+            });
+            stats.num_files += 1;
+            stats.num_entries += file.num_entries;
+            stats.num_deletions += file.num_deletions;
+        }
+        Ok(levels.into_values().collect())
+    }
+
     /// Delete sst files whose keys are entirely in the given range.
     ///
     /// Could leave some keys in the range which are in files which are not
@@ -2011,6 +2579,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         from: K,
         to: K,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         let from = from.as_ref();
         let to = to.as_ref();
         unsafe {
@@ -2026,6 +2595,52 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Same as `delete_file_in_range_cf`, but for several ranges, possibly of different column
+    /// families.
+    ///
+    /// The ranges are deleted one by one, so this isn't atomic. A failure doesn't stop the
+    /// remaining ranges from being deleted; the errors of all failed ranges are returned
+    /// together.
+    pub fn delete_files_in_ranges<'a, K, I, W>(&self, ranges: I) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (&'a W, K, K)>,
+        W: AsColumnFamilyRef + 'a,
+    {
+        let errors: Vec<_> = ranges
+            .into_iter()
+            .filter_map(|(cf, from, to)| self.delete_file_in_range_cf(cf, from, to).err())
+            .map(Error::into_string)
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::new(errors.join("; ")))
+        }
+    }
+
+    /// Loads the blocks holding the keys in the range [`from`, `to`) of a column family into
+    /// the block cache, e.g. to get predictable read latencies right after opening.
+    ///
+    /// This reads the whole range, so its cost is proportional to the size of the range.
+    /// Blocks only stay cached as long as the block cache has room for them.
+    pub fn warm_cache_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        from: K,
+        to: K,
+    ) -> Result<(), Error> {
+        let mut readopts = ReadOptions::default();
+        readopts.fill_cache(true);
+        readopts.set_iterate_range(from.as_ref()..to.as_ref());
+        let mut iter = self.raw_iterator_cf_opt(cf, readopts);
+        iter.seek_to_first();
+        while iter.valid() {
+            iter.next();
+        }
+        iter.status()
+    }
+
     /// Request stopping background work, if wait is true wait until it's done.
     pub fn cancel_all_background_work(&self, wait: bool) {
         unsafe {
@@ -2033,6 +2648,40 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Closes the database, surfacing flush and background errors which would be swallowed when
+    /// it's just dropped.
+    ///
+    /// If `flush` is true, the memtables of all column families are flushed first, and the first
+    /// flush error is returned. Background work is then cancelled and waited for, which also
+    /// flushes unpersisted data unless `avoid_flush_during_shutdown` is set. If any background
+    /// flush or compaction failed while the database was open, an error reporting their number
+    /// is returned. The database is closed in either case; errors of the final close itself are
+    /// not reported by the C API.
+    pub fn close(self, flush: bool) -> Result<(), Error> {
+        if flush {
+            let flushopts = FlushOptions::default();
+            // Flushes the default column family, which isn't listed below.
+            self.flush_opt(&flushopts)?;
+            for cf in self.cfs.cf_handles_internal() {
+                unsafe {
+                    ffi_try!(ffi::rocksdb_flush_cf(
+                        self.inner.inner(),
+                        flushopts.inner,
+                        cf
+                    ));
+                }
+            }
+        }
+        self.cancel_all_background_work(true);
+        match self.property_int_value(properties::BACKGROUND_ERRORS)? {
+            Some(count) if count > 0 => Err(Error::new(format!(
+                "{} background error(s) occurred before the database was closed",
+                count
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     fn drop_column_family<C>(
         &self,
         cf_inner: *mut ffi::rocksdb_column_family_handle_t,
@@ -2056,9 +2705,13 @@ impl<I: DBInner> DBCommon<SingleThreaded, I> {
     /// Creates column family with given name and options
     pub fn create_cf<N: AsRef<str>>(&mut self, name: N, opts: &Options) -> Result<(), Error> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
-        self.cfs
-            .cfs
-            .insert(name.as_ref().to_string(), ColumnFamily { inner });
+        self.cfs.cfs.insert(
+            name.as_ref().to_string(),
+            ColumnFamily {
+                inner,
+                comparator: opts.comparator,
+            },
+        );
         Ok(())
     }
 
@@ -2083,7 +2736,7 @@ impl<I: DBInner> DBCommon<MultiThreaded, I> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
         self.cfs.cfs.write().unwrap().insert(
             name.as_ref().to_string(),
-            Arc::new(UnboundColumnFamily { inner }),
+            Arc::new(UnboundColumnFamily::new(inner, opts.comparator)),
         );
         Ok(())
     }
@@ -2092,6 +2745,7 @@ impl<I: DBInner> DBCommon<MultiThreaded, I> {
     /// family map. This avoids needing `&mut self` reference
     pub fn drop_cf(&self, name: &str) -> Result<(), Error> {
         if let Some(cf) = self.cfs.cfs.write().unwrap().remove(name) {
+            cf.mark_dropped();
             self.drop_column_family(cf.inner, cf)
         } else {
             Err(Error::new(format!("Invalid column family: {}", name)))
@@ -2143,6 +2797,33 @@ pub struct LiveFile {
     pub num_deletions: u64,
 }
 
+/// Takes ownership of the value returned by `rocksdb_key_may_exist(_cf)`, if it was found.
+unsafe fn take_found_value(
+    val: *mut c_char,
+    val_len: size_t,
+    value_found: c_uchar,
+) -> Option<Vec<u8>> {
+    if val.is_null() {
+        return None;
+    }
+    let value = raw_data(val, val_len);
+    ffi::rocksdb_free(val as *mut c_void);
+    value.filter(|_| value_found != 0)
+}
+
+/// The numbers of files, entries and deletions of a level, see [`DBCommon::level_key_stats_cf`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelKeyStats {
+    /// The level
+    pub level: i32,
+    /// Number of table files at the level
+    pub num_files: usize,
+    /// Number of entries in the files of the level, including deletions
+    pub num_entries: u64,
+    /// Number of deletions in the files of the level
+    pub num_deletions: u64,
+}
+
 fn convert_options(opts: &[(&str, &str)]) -> Result<Vec<(CString, CString)>, Error> {
     opts.iter()
         .map(|(name, value)| {
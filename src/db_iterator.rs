@@ -13,8 +13,9 @@
 // limitations under the License.
 
 use crate::{
+    column_family::column_family_dropped,
     db::{DBAccess, DB},
-    ffi, Error, ReadOptions, WriteBatch,
+    ffi, AsColumnFamilyRef, Error, ReadOptions, WriteBatch,
 };
 use libc::{c_char, c_uchar, size_t};
 use std::{marker::PhantomData, slice};
@@ -83,25 +84,33 @@ pub struct DBRawIteratorWithThreadMode<'a, D: DBAccess> {
     /// point to vectors we own.  See issue #660.
     _readopts: ReadOptions,
 
+    /// Whether the column family iterated over was dropped before the iterator was created.
+    /// Such an iterator is never valid and reports a `ColumnFamilyDropped` status.
+    cf_dropped: bool,
+
     db: PhantomData<&'a D>,
 }
 
 impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
     pub(crate) fn new(db: &D, readopts: ReadOptions) -> Self {
         let inner = unsafe { db.create_iterator(&readopts) };
-        Self::from_inner(inner, readopts)
+        Self::from_inner(inner, readopts, false)
     }
 
     pub(crate) fn new_cf(
         db: &'a D,
-        cf_handle: *mut ffi::rocksdb_column_family_handle_t,
+        cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> Self {
-        let inner = unsafe { db.create_iterator_cf(cf_handle, &readopts) };
-        Self::from_inner(inner, readopts)
+        let inner = unsafe { db.create_iterator_cf(cf_handle.inner(), &readopts) };
+        Self::from_inner(inner, readopts, cf_handle.is_dropped())
     }
 
-    fn from_inner(inner: *mut ffi::rocksdb_iterator_t, readopts: ReadOptions) -> Self {
+    fn from_inner(
+        inner: *mut ffi::rocksdb_iterator_t,
+        readopts: ReadOptions,
+        cf_dropped: bool,
+    ) -> Self {
         // This unwrap will never fail since rocksdb_create_iterator and
         // rocksdb_create_iterator_cf functions always return non-null. They
         // use new and deference the result so any nulls would end up with SIGSEGV
@@ -110,6 +119,7 @@ impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
         Self {
             inner,
             _readopts: readopts,
+            cf_dropped,
             db: PhantomData,
         }
     }
@@ -121,7 +131,7 @@ impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
     /// returned `false`, use the [`status`](DBRawIteratorWithThreadMode::status) method. `status` will never
     /// return an error when `valid` is `true`.
     pub fn valid(&self) -> bool {
-        unsafe { ffi::rocksdb_iter_valid(self.inner.as_ptr()) != 0 }
+        !self.cf_dropped && unsafe { ffi::rocksdb_iter_valid(self.inner.as_ptr()) != 0 }
     }
 
     /// Returns an error `Result` if the iterator has encountered an error
@@ -130,6 +140,9 @@ impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
     ///
     /// Performing a seek will discard the current status.
     pub fn status(&self) -> Result<(), Error> {
+        if self.cf_dropped {
+            return Err(column_family_dropped());
+        }
         unsafe {
             ffi_try!(ffi::rocksdb_iter_get_error(self.inner.as_ptr()));
         }
@@ -430,7 +443,7 @@ impl<'a, D: DBAccess> DBIteratorWithThreadMode<'a, D> {
 
     pub(crate) fn new_cf(
         db: &'a D,
-        cf_handle: *mut ffi::rocksdb_column_family_handle_t,
+        cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> Self {
@@ -553,7 +566,7 @@ impl Iterator for DBWALIterator {
         if self.valid() {
             let mut seq: u64 = 0;
             let inner = unsafe { ffi::rocksdb_wal_iter_get_batch(self.inner, &mut seq) };
-            Some(Ok((seq, WriteBatch { inner })))
+            Some(Ok((seq, WriteBatch::from_inner(inner))))
         } else {
             self.status().err().map(Result::Err)
         }
@@ -0,0 +1,143 @@
+//! Detection of the LOCK file RocksDB holds while a database is open for writing.
+//!
+//! RocksDB takes an OS advisory lock on `<path>/LOCK`. On Unix this is a POSIX record lock,
+//! which a process can't see when it holds the lock itself, and which is released as soon as
+//! the process closes *any* descriptor of the file. Databases opened for writing by this
+//! process are therefore tracked in a registry, and the LOCK file is only probed for paths
+//! which aren't in it: probing the LOCK file of a database this process holds open would
+//! release its lock.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, Once, PoisonError};
+
+/// Returns the paths of the databases this process has opened for writing. A path appears
+/// once per open, so failed attempts to open an already open database don't unregister it.
+fn open_paths() -> MutexGuard<'static, Vec<PathBuf>> {
+    // `Mutex::new` is only `const` since Rust 1.63, so the registry is initialized lazily.
+    static INIT: Once = Once::new();
+    static mut OPEN_PATHS: Option<Mutex<Vec<PathBuf>>> = None;
+    let paths = unsafe {
+        INIT.call_once(|| OPEN_PATHS = Some(Mutex::new(Vec::new())));
+        (*ptr::addr_of!(OPEN_PATHS)).as_ref().unwrap()
+    };
+    paths.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn registry_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Marks the database at a path as opened for writing by this process, until dropped.
+///
+/// Must be acquired before RocksDB takes the LOCK file and dropped after it released it.
+pub(crate) struct ProcessLock {
+    path: PathBuf,
+}
+
+impl ProcessLock {
+    pub(crate) fn acquire(path: &Path) -> Self {
+        let path = registry_key(path);
+        open_paths().push(path.clone());
+        Self { path }
+    }
+}
+
+impl Drop for ProcessLock {
+    fn drop(&mut self) {
+        let mut paths = open_paths();
+        if let Some(pos) = paths.iter().position(|p| *p == self.path) {
+            paths.swap_remove(pos);
+        }
+    }
+}
+
+/// Returns whether the LOCK file of the database at `path` is held, by this process or by
+/// another one. Never creates or modifies anything under `path`.
+///
+/// The LOCK file is only opened when `path` isn't in the registry, that is when this process
+/// doesn't hold the database open for writing, as closing the probe would release the lock.
+pub(crate) fn is_locked(path: &Path) -> bool {
+    // Hold the registry while probing, so that no database of this process can take the lock
+    // between the check and the probe closing its descriptor.
+    let paths = open_paths();
+    if paths.contains(&registry_key(path)) {
+        return true;
+    }
+    match File::open(path.join("LOCK")) {
+        Ok(file) => lock_file_is_held(&file).unwrap_or(false),
+        Err(err) => is_sharing_violation(&err),
+    }
+}
+
+#[cfg(unix)]
+fn lock_file_is_held(file: &File) -> io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // RocksDB write-locks the whole file, so ask whether such a lock could be placed.
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as _;
+    lock.l_whence = libc::SEEK_SET as _;
+    lock.l_start = 0;
+    lock.l_len = 0;
+    if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETLK, &mut lock) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(lock.l_type != libc::F_UNLCK as _)
+}
+
+#[cfg(not(unix))]
+fn lock_file_is_held(_file: &File) -> io::Result<bool> {
+    // RocksDB opens the LOCK file exclusively, so being able to open it means it isn't held.
+    Ok(false)
+}
+
+#[cfg(windows)]
+fn is_sharing_violation(err: &io::Error) -> bool {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    err.raw_os_error() == Some(ERROR_SHARING_VIOLATION)
+}
+
+#[cfg(not(windows))]
+fn is_sharing_violation(_err: &io::Error) -> bool {
+    false
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::{Options, DB};
+    use std::os::unix::fs::MetadataExt;
+
+    /// Returns whether `/proc/locks` lists a lock of this process on the file.
+    fn holds_posix_lock(file: &Path) -> bool {
+        let pid = std::process::id().to_string();
+        let inode = fs::metadata(file).unwrap().ino().to_string();
+        fs::read_to_string("/proc/locks")
+            .unwrap()
+            .lines()
+            .any(|line| {
+                // For example `1: POSIX  ADVISORY  WRITE 1234 08:01:5678 0 EOF`.
+                let fields: Vec<_> = line.split_whitespace().collect();
+                fields.get(4) == Some(&pid.as_str())
+                    && fields.get(5).and_then(|id| id.rsplit(':').next()) == Some(inode.as_str())
+            })
+    }
+
+    #[test]
+    fn is_locked_keeps_the_lock_of_an_open_database() {
+        let path = Path::new("_rust_rocksdb_db_lock_keeps_the_lock");
+        {
+            let _db = DB::open_default(path).unwrap();
+            let lock_file = path.join("LOCK");
+            assert!(holds_posix_lock(&lock_file));
+            assert!(is_locked(path));
+            assert!(is_locked(path));
+            assert!(holds_posix_lock(&lock_file));
+        }
+        assert!(!is_locked(path));
+        let _ = DB::destroy(&Options::default(), path);
+    }
+}
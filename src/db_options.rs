@@ -273,6 +273,8 @@ impl BlockBasedOptionsMustOutliveDB {
 pub struct Options {
     pub(crate) inner: *mut ffi::rocksdb_options_t,
     pub(crate) outlive: OptionsMustOutliveDB,
+    // Kept so that the bounds of range deletions can be checked before they are written.
+    pub(crate) comparator: Option<CompareFn>,
 }
 
 /// Optionally disable WAL or sync for this write.
@@ -412,6 +414,7 @@ impl Clone for Options {
         Self {
             inner,
             outlive: self.outlive.clone(),
+            comparator: self.comparator,
         }
     }
 }
@@ -538,6 +541,10 @@ impl BlockBasedOptions {
     ///
     /// If set, use the specified cache for blocks.
     /// By default, rocksdb will automatically create and use an 8MB internal cache.
+    ///
+    /// Each column family may have its own cache: set it on the block based options of that
+    /// column family's `Options` before passing them to `create_cf` or a `ColumnFamilyDescriptor`.
+    /// This allows e.g. giving a hot column family a large cache and a cold one a small cache.
     pub fn set_block_cache(&mut self, cache: &Cache) {
         unsafe {
             ffi::rocksdb_block_based_options_set_block_cache(self.inner, cache.0.inner);
@@ -1393,6 +1400,10 @@ impl Options {
     /// The client must ensure that the comparator supplied here has the same
     /// name and orders keys *exactly* the same as the comparator provided to
     /// previous open calls on the same DB.
+    ///
+    /// The C API does not support the `FindShortestSeparator`/`FindShortSuccessor` key
+    /// shortening hooks of comparators, so index blocks of tables using a custom comparator
+    /// always store full keys.
     pub fn set_comparator(&mut self, name: impl CStrLike, compare_fn: CompareFn) {
         let cb = Box::new(ComparatorCallback {
             name: name.into_c_string().unwrap(),
@@ -1408,6 +1419,7 @@ impl Options {
             );
             ffi::rocksdb_options_set_comparator(self.inner, cmp);
         }
+        self.comparator = Some(compare_fn);
     }
 
     pub fn set_prefix_extractor(&mut self, prefix_extractor: SliceTransform) {
@@ -2305,6 +2317,9 @@ impl Options {
     /// * new sizeof(new_value) <= sizeof(old_value)
     /// * old_value for that key is a put i.e. kTypeValue
     ///
+    /// Not compatible with concurrent memtable writes, so opening the database fails unless
+    /// `set_allow_concurrent_memtable_write(false)` is also set.
+    ///
     /// Default: false.
     pub fn set_inplace_update_support(&mut self, enabled: bool) {
         unsafe {
@@ -2549,6 +2564,8 @@ impl Options {
     /// column families whose memtables are backed by the oldest live WAL file
     /// (i.e. the ones that are causing all the space amplification).
     ///
+    /// Only takes effect when the database has more than one column family.
+    ///
     /// Default: `0`
     ///
     /// # Examples
@@ -3137,6 +3154,7 @@ impl Default for Options {
             Self {
                 inner: opts,
                 outlive: OptionsMustOutliveDB::default(),
+                comparator: None,
             }
         }
     }
@@ -3404,6 +3422,9 @@ impl ReadOptions {
     /// iterate_upper_bound, prefix_same_as_start only works within a prefix
     /// but in both directions.
     ///
+    /// To scan across prefixes instead, leave this disabled and enable
+    /// [`set_total_order_seek`](Self::set_total_order_seek).
+    ///
     /// Default: false
     pub fn set_prefix_same_as_start(&mut self, v: bool) {
         unsafe {
@@ -3418,6 +3439,13 @@ impl ReadOptions {
     /// If true when calling Get(), we also skip prefix bloom when reading from
     /// block based table. It provides a way to read existing data after
     /// changing implementation of prefix extractor.
+    ///
+    /// Iterators created with this option ignore the prefix extractor: they
+    /// return all keys in total order and don't consult prefix bloom filters,
+    /// so this is the option to use for full scans. It also makes
+    /// [`set_prefix_same_as_start`](Self::set_prefix_same_as_start) ineffective.
+    ///
+    /// Default: false
     pub fn set_total_order_seek(&mut self, v: bool) {
         unsafe {
             ffi::rocksdb_readoptions_set_total_order_seek(self.inner, c_uchar::from(v));
@@ -3508,6 +3536,35 @@ impl ReadOptions {
             ffi::rocksdb_readoptions_set_pin_data(self.inner, c_uchar::from(v));
         }
     }
+
+    /// Sets a deadline for the completion of a point lookup (`get` and `multi_get`), as an
+    /// absolute time in microseconds since the epoch. A read which doesn't complete in time
+    /// fails with an error of the [`TimedOut`] kind. This is best effort: the deadline is only
+    /// checked between I/Os.
+    ///
+    /// The deadline doesn't bound the time transactions wait for a lock, e.g. in
+    /// `get_for_update`. Use [`TransactionOptions::set_lock_timeout`] for that.
+    ///
+    /// Default: 0 (no deadline)
+    ///
+    /// [`TimedOut`]: crate::ErrorKind::TimedOut
+    /// [`TransactionOptions::set_lock_timeout`]: crate::TransactionOptions::set_lock_timeout
+    pub fn set_deadline(&mut self, microseconds: u64) {
+        unsafe {
+            ffi::rocksdb_readoptions_set_deadline(self.inner, microseconds);
+        }
+    }
+
+    /// Sets a timeout in microseconds for each file read done for the read. The timeout is
+    /// passed to the file system, which may not enforce it: the default POSIX one doesn't. Like
+    /// [`set_deadline`](Self::set_deadline), it doesn't apply to lock waits of transactions.
+    ///
+    /// Default: 0 (no timeout)
+    pub fn set_io_timeout(&mut self, microseconds: u64) {
+        unsafe {
+            ffi::rocksdb_readoptions_set_io_timeout(self.inner, microseconds);
+        }
+    }
 }
 
 impl Default for ReadOptions {
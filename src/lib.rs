@@ -76,6 +76,7 @@
 mod ffi_util;
 
 pub mod backup;
+mod batch_writer;
 pub mod checkpoint;
 mod column_family;
 pub mod compaction_filter;
@@ -83,6 +84,7 @@ pub mod compaction_filter_factory;
 mod comparator;
 pub mod db;
 mod db_iterator;
+mod db_lock;
 mod db_options;
 mod db_pinnable_slice;
 mod iter_range;
@@ -96,14 +98,15 @@ mod transactions;
 mod write_batch;
 
 pub use crate::{
+    batch_writer::BatchWriter,
     column_family::{
         AsColumnFamilyRef, BoundColumnFamily, ColumnFamily, ColumnFamilyDescriptor,
         ColumnFamilyRef, DEFAULT_COLUMN_FAMILY_NAME,
     },
     compaction_filter::Decision as CompactionDecision,
     db::{
-        DBAccess, DBCommon, DBWithThreadMode, LiveFile, MultiThreaded, SingleThreaded, ThreadMode,
-        DB,
+        DBAccess, DBCommon, DBWithThreadMode, LevelKeyStats, LiveFile, MultiThreaded, OpenBuilder,
+        SingleThreaded, ThreadMode, DB,
     },
     db_iterator::{
         DBIterator, DBIteratorWithThreadMode, DBRawIterator, DBRawIteratorWithThreadMode,
@@ -113,7 +116,7 @@ pub use crate::{
         BlockBasedIndexType, BlockBasedOptions, BottommostLevelCompaction, Cache, CompactOptions,
         CuckooTableOptions, DBCompactionStyle, DBCompressionType, DBPath, DBRecoveryMode,
         DataBlockIndexType, Env, FifoCompactOptions, FlushOptions, IngestExternalFileOptions,
-        LogLevel, MemtableFactory, Options, PlainTableFactoryOptions, ReadOptions,
+        LogLevel, MemtableFactory, Options, PlainTableFactoryOptions, ReadOptions, ReadTier,
         UniversalCompactOptions, UniversalCompactionStopStyle, WriteOptions,
     },
     db_pinnable_slice::DBPinnableSlice,
@@ -122,13 +125,13 @@ pub use crate::{
     merge_operator::MergeOperands,
     perf::{PerfContext, PerfMetric, PerfStatsLevel},
     slice_transform::SliceTransform,
-    snapshot::{Snapshot, SnapshotWithThreadMode},
+    snapshot::{Snapshot, SnapshotReadOptions, SnapshotWithThreadMode},
     sst_file_writer::SstFileWriter,
     transactions::{
-        OptimisticTransactionDB, OptimisticTransactionDBInner, OptimisticTransactionOptions, Transaction, TransactionDB,
-        TransactionDBOptions, TransactionOptions,
+        OptimisticTransactionDB, OptimisticTransactionDBInner, OptimisticTransactionOptions,
+        PendingWrite, Transaction, TransactionDB, TransactionDBOptions, TransactionOptions,
     },
-    write_batch::{WriteBatch, WriteBatchIterator, WriteBatchWithTransaction},
+    write_batch::{WriteBatch, WriteBatchIterator, WriteBatchWithTransaction, WriteType},
 };
 
 use librocksdb_sys as ffi;
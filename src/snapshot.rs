@@ -17,11 +17,17 @@ use crate::{
     DBRawIteratorWithThreadMode, Error, IteratorMode, ReadOptions, DB,
 };
 
+use std::{marker::PhantomData, ops::Deref};
+
 /// A type alias to keep compatibility. See [`SnapshotWithThreadMode`] for details
 pub type Snapshot<'a> = SnapshotWithThreadMode<'a, DB>;
 
 /// A consistent view of the database at the point of creation.
 ///
+/// The snapshot is released when this value is dropped, so there is no need to release
+/// it manually. Keeping it alive pins every key version it can see, which prevents
+/// compaction from dropping them.
+///
 /// # Examples
 ///
 /// ```
@@ -87,7 +93,7 @@ impl<'a, D: DBAccess> SnapshotWithThreadMode<'a, D> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<D> {
         readopts.set_snapshot(self);
-        DBIteratorWithThreadMode::new_cf(self.db, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self.db, cf_handle, readopts, mode)
     }
 
     /// Creates a raw iterator over the data in this snapshot, using the default read options.
@@ -120,7 +126,7 @@ impl<'a, D: DBAccess> SnapshotWithThreadMode<'a, D> {
         mut readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<D> {
         readopts.set_snapshot(self);
-        DBRawIteratorWithThreadMode::new_cf(self.db, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self.db, cf_handle, readopts)
     }
 
     /// Returns the bytes associated with a key value with default read options.
@@ -267,3 +273,51 @@ impl<'a, D: DBAccess> Drop for SnapshotWithThreadMode<'a, D> {
 /// immutable and can be safely shared between threads.
 unsafe impl<'a, D: DBAccess> Send for SnapshotWithThreadMode<'a, D> {}
 unsafe impl<'a, D: DBAccess> Sync for SnapshotWithThreadMode<'a, D> {}
+
+/// Read options which read from a snapshot, created with [`ReadOptions::with_snapshot`].
+///
+/// Unlike plain `ReadOptions`, these borrow the snapshot, so they cannot be used after the
+/// snapshot has been released. They dereference to `ReadOptions`, so they can be passed
+/// wherever `&ReadOptions` is expected.
+///
+/// ```
+/// use rocksdb::{Options, ReadOptions, DB};
+///
+/// let path = "_path_for_rocksdb_storage_snapshot_read_options";
+/// {
+///     let db = DB::open_default(path).unwrap();
+///     db.put(b"k1", b"v1").unwrap();
+///     let snapshot = db.snapshot();
+///     let readopts = ReadOptions::default().with_snapshot(&snapshot);
+///     db.put(b"k1", b"v2").unwrap();
+///     assert_eq!(db.get_opt(b"k1", &readopts).unwrap().unwrap(), b"v1");
+/// }
+/// let _ = DB::destroy(&Options::default(), path);
+/// ```
+pub struct SnapshotReadOptions<'s> {
+    readopts: ReadOptions,
+    snapshot: PhantomData<&'s ()>,
+}
+
+impl ReadOptions {
+    /// Ties these read options to `snapshot`, making reads with them see the database as of
+    /// the time the snapshot was taken.
+    pub fn with_snapshot<'s, D: DBAccess>(
+        mut self,
+        snapshot: &'s SnapshotWithThreadMode<D>,
+    ) -> SnapshotReadOptions<'s> {
+        self.set_snapshot(snapshot);
+        SnapshotReadOptions {
+            readopts: self,
+            snapshot: PhantomData,
+        }
+    }
+}
+
+impl<'s> Deref for SnapshotReadOptions<'s> {
+    type Target = ReadOptions;
+
+    fn deref(&self) -> &ReadOptions {
+        &self.readopts
+    }
+}
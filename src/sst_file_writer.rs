@@ -20,6 +20,8 @@ use std::{ffi::CString, marker::PhantomData, path::Path};
 
 /// SstFileWriter is used to create sst files that can be added to database later
 /// All keys in files generated by SstFileWriter will have sequence number = 0.
+/// Keys must be added in increasing order of the comparator; adding a key that is
+/// not after the previous one returns an error and leaves the file unchanged.
 pub struct SstFileWriter<'a> {
     pub(crate) inner: *mut ffi::rocksdb_sstfilewriter_t,
     // Options are needed to be alive when calling open(),
@@ -20,5 +20,5 @@ mod transaction_db;
 
 pub use optimistic_transaction_db::{OptimisticTransactionDB, OptimisticTransactionDBInner};
 pub use options::{OptimisticTransactionOptions, TransactionDBOptions, TransactionOptions};
-pub use transaction::Transaction;
+pub use transaction::{PendingWrite, Transaction};
 pub use transaction_db::TransactionDB;
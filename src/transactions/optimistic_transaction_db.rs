@@ -13,16 +13,20 @@
 // limitations under the License.
 //
 
-use std::{collections::BTreeMap, ffi::CString, fs, iter, marker::PhantomData, path::Path, ptr};
+use std::{cell::Cell, collections::BTreeMap, ffi::CString, fs, iter, path::Path, ptr};
 
 use libc::{c_char, c_int};
 
 use crate::{
-    db::DBCommon, db::DBInner, ffi, ffi_util::to_cpath, write_batch::WriteBatchWithTransaction,
-    ColumnFamilyDescriptor, Error, OptimisticTransactionOptions, Options, ThreadMode, Transaction,
-    WriteOptions, DEFAULT_COLUMN_FAMILY_NAME,
+    db::default_cf_comparator, db::DBCommon, db::DBInner, db_lock::ProcessLock, ffi,
+    ffi_util::to_cpath, write_batch::WriteBatchWithTransaction, AsColumnFamilyRef,
+    ColumnFamilyDescriptor, Error, ErrorKind, OptimisticTransactionOptions, Options, ThreadMode,
+    Transaction, WriteOptions, DEFAULT_COLUMN_FAMILY_NAME,
 };
 
+// How often `compare_and_swap_cf` retries a commit which failed because of a concurrent write.
+const COMPARE_AND_SWAP_RETRIES: usize = 16;
+
 /// A type alias to RocksDB Optimistic Transaction DB.
 ///
 /// Please read the official
@@ -129,6 +133,7 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
         let outlive = iter::once(opts.outlive.clone())
             .chain(cfs.iter().map(|cf| cf.options.outlive.clone()))
             .collect();
+        let default_comparator = default_cf_comparator(opts, &cfs);
 
         let cpath = to_cpath(&path)?;
 
@@ -138,6 +143,7 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
                 e
             )));
         }
+        let lock = ProcessLock::acquire(path.as_ref());
 
         let db: *mut ffi::rocksdb_optimistictransactiondb_t;
         let mut cf_map = BTreeMap::new();
@@ -181,7 +187,7 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
             }
 
             for (cf_desc, inner) in cfs_v.iter().zip(cfhandles) {
-                cf_map.insert(cf_desc.name.clone(), inner);
+                cf_map.insert(cf_desc.name.clone(), (inner, cf_desc.options.comparator));
             }
         }
 
@@ -203,6 +209,8 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
             T::new_cf_map_internal(cf_map),
             path.as_ref().to_path_buf(),
             outlive,
+            default_comparator,
+            Some(lock),
         ))
     }
 
@@ -263,7 +271,8 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
                     std::ptr::null_mut(),
                 )
             },
-            _marker: PhantomData::default(),
+            savepoints: Cell::new(0),
+            db: self,
         }
     }
 
@@ -272,6 +281,7 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
         batch: WriteBatchWithTransaction<true>,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        batch.check_cf_not_dropped()?;
         unsafe {
             ffi_try!(ffi::rocksdb_optimistictransactiondb_write(
                 self.inner.db,
@@ -291,4 +301,46 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
         wo.disable_wal(true);
         self.write_opt(batch, &wo)
     }
+
+    /// Atomically replaces the value of `key` in a column family with `new`, if its current
+    /// value is `expected` (`None` meaning that the key must not exist).
+    ///
+    /// Returns whether the value was replaced. This is done in an optimistic transaction,
+    /// which is retried when its commit fails with [`ErrorKind::Busy`] because the key was
+    /// written concurrently between the comparison and the commit, or with
+    /// [`ErrorKind::TryAgain`] because the memtable history was too short to check for such
+    /// writes. After 16 retries the error of the last commit is returned.
+    ///
+    /// This is only available on `OptimisticTransactionDB`: a plain [`DB`] can't detect a write
+    /// made by another thread between the read and the write, so it couldn't do the swap
+    /// atomically. Open the database with [`open`](Self::open) to use it.
+    ///
+    /// [`DB`]: crate::DB
+    pub fn compare_and_swap_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        expected: Option<&[u8]>,
+        new: &[u8],
+    ) -> Result<bool, Error> {
+        let key = key.as_ref();
+        let mut retries = 0;
+        loop {
+            let txn = self.transaction();
+            if txn.get_pinned_for_update_cf(cf, key, true)?.as_deref() != expected {
+                return Ok(false);
+            }
+            txn.put_cf(cf, key, new)?;
+            match txn.commit() {
+                Ok(()) => return Ok(true),
+                Err(err)
+                    if matches!(err.kind(), ErrorKind::Busy | ErrorKind::TryAgain)
+                        && retries < COMPARE_AND_SWAP_RETRIES =>
+                {
+                    retries += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
 }
@@ -38,6 +38,13 @@ impl TransactionOptions {
         TransactionOptions::default()
     }
 
+    /// Despite its name, this sets whether the transaction uses a snapshot, exactly like
+    /// [`set_snapshot`](Self::set_snapshot). It does not change whether the transaction may
+    /// skip `prepare`: the C API of RocksDB has no setter for `skip_prepare`.
+    #[deprecated(
+        since = "0.19.0",
+        note = "This sets set_snapshot, not skip_prepare. Use set_snapshot instead"
+    )]
     pub fn set_skip_prepare(&mut self, skip_prepare: bool) {
         unsafe {
             ffi::rocksdb_transaction_options_set_set_snapshot(self.inner, u8::from(skip_prepare));
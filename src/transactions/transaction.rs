@@ -13,12 +13,16 @@
 // limitations under the License.
 //
 
-use std::{marker::PhantomData, ptr};
+use std::{cell::Cell, ptr};
 
 use crate::{
+    column_family::{check_not_dropped, column_family_dropped},
     db::{convert_values, DBAccess},
-    ffi, AsColumnFamilyRef, DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode,
+    ffi,
+    write_batch::{count_operations, decode_operations},
+    AsColumnFamilyRef, DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode,
     Direction, Error, IteratorMode, ReadOptions, SnapshotWithThreadMode, WriteBatchWithTransaction,
+    WriteType,
 };
 use libc::{c_char, c_void, size_t};
 
@@ -30,11 +34,24 @@ use libc::{c_char, c_void, size_t};
 /// [`OptimisticTransactionDB`]: crate::OptimisticTransactionDB
 pub struct Transaction<'db, DB> {
     pub(crate) inner: *mut ffi::rocksdb_transaction_t,
-    pub(crate) _marker: PhantomData<&'db DB>,
+    pub(crate) savepoints: Cell<usize>,
+    pub(crate) db: &'db DB,
 }
 
 unsafe impl<'db, DB> Send for Transaction<'db, DB> {}
 
+/// A write pending in a [`Transaction`], as returned by [`Transaction::pending_iterator`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingWrite {
+    pub write_type: WriteType,
+    /// The ID of the column family written to, 0 being the default column family.
+    pub cf_id: u32,
+    /// The written key, or the begin key of a delete range.
+    pub key: Box<[u8]>,
+    /// The value of a put or merge, or the end key of a delete range.
+    pub value: Option<Box<[u8]>>,
+}
+
 impl<'db, DB> DBAccess for Transaction<'db, DB> {
     unsafe fn create_snapshot(&self) -> *const ffi::rocksdb_snapshot_t {
         ffi::rocksdb_transaction_get_snapshot(self.inner)
@@ -121,6 +138,11 @@ impl<'db, DB> Transaction<'db, DB> {
     ///
     /// May return any error that could be returned by `DB::write`.
     ///
+    /// The write uses the [`WriteOptions`] the transaction was created with, as the C API
+    /// can't take write options at commit time. To e.g. sync only some commits, create those
+    /// transactions with [`WriteOptions::set_sync`] enabled (see
+    /// [`TransactionDB::transaction_opt`]).
+    ///
     /// If this transaction was created by a [`TransactionDB`], an error of
     /// the [`Expired`] kind may be returned if this transaction has
     /// lived longer than expiration time in [`TransactionOptions`].
@@ -134,6 +156,9 @@ impl<'db, DB> Transaction<'db, DB> {
     /// [`Expired`]: crate::ErrorKind::Expired
     /// [`TransactionOptions`]: crate::TransactionOptions
     /// [`TransactionDB`]: crate::TransactionDB
+    /// [`TransactionDB::transaction_opt`]: crate::TransactionDB::transaction_opt
+    /// [`WriteOptions`]: crate::WriteOptions
+    /// [`WriteOptions::set_sync`]: crate::WriteOptions::set_sync
     /// [`OptimisticTransactionDB`]: crate::OptimisticTransactionDB
     /// [`Busy`]: crate::ErrorKind::Busy
     /// [`TryAgain`]: crate::ErrorKind::TryAgain
@@ -145,9 +170,15 @@ impl<'db, DB> Transaction<'db, DB> {
         Ok(())
     }
 
-    pub fn set_name(&self, name: &[u8]) -> Result<(), Error> {
-        let ptr = name.as_ptr();
-        let len = name.len();
+    /// Sets the name of the transaction, which is required for two-phase commit.
+    ///
+    /// The name must be set before [`prepare`] and be unique among the transactions of the
+    /// DB, as prepared transactions are identified by it on recovery.
+    ///
+    /// [`prepare`]: Self::prepare
+    pub fn set_name<N: AsRef<[u8]>>(&self, name: N) -> Result<(), Error> {
+        let ptr = name.as_ref().as_ptr();
+        let len = name.as_ref().len();
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_set_name(
                 self.inner, ptr as _, len as _
@@ -157,6 +188,9 @@ impl<'db, DB> Transaction<'db, DB> {
         Ok(())
     }
 
+    /// Returns the name of the transaction, or `None` if [`set_name`] hasn't been called.
+    ///
+    /// [`set_name`]: Self::set_name
     pub fn get_name(&self) -> Option<Vec<u8>> {
         unsafe {
             let mut name_len = 0;
@@ -167,11 +201,27 @@ impl<'db, DB> Transaction<'db, DB> {
                 let mut vec = vec![0; name_len];
                 std::ptr::copy_nonoverlapping(name as *mut u8, vec.as_mut_ptr(), name_len as usize);
                 ffi::rocksdb_free(name as *mut c_void);
-                Some(vec)
+                // An unnamed transaction has an empty name.
+                if vec.is_empty() {
+                    None
+                } else {
+                    Some(vec)
+                }
             }
         }
     }
 
+    /// Prepares the transaction for two-phase commit.
+    ///
+    /// The transaction must have been named with [`set_name`] first. A prepared transaction
+    /// is written to the WAL and survives a crash: it's returned by
+    /// [`TransactionDB::prepared_transactions`] after the DB is reopened. It is only finalized
+    /// by a following [`commit`], or discarded by [`rollback`].
+    ///
+    /// [`set_name`]: Self::set_name
+    /// [`commit`]: Self::commit
+    /// [`rollback`]: Self::rollback
+    /// [`TransactionDB::prepared_transactions`]: crate::TransactionDB::prepared_transactions
     pub fn prepare(&self) -> Result<(), Error> {
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_prepare(self.inner));
@@ -188,11 +238,16 @@ impl<'db, DB> Transaction<'db, DB> {
     }
 
     /// Discard all batched writes in this transaction.
+    ///
+    /// This also removes all save points set with [`set_savepoint`].
+    ///
+    /// [`set_savepoint`]: Self::set_savepoint
     pub fn rollback(&self) -> Result<(), Error> {
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_rollback(self.inner));
-            Ok(())
         }
+        self.savepoints.set(0);
+        Ok(())
     }
 
     /// Record the state of the transaction for future calls to [`rollback_to_savepoint`].
@@ -203,6 +258,7 @@ impl<'db, DB> Transaction<'db, DB> {
         unsafe {
             ffi::rocksdb_transaction_set_savepoint(self.inner);
         }
+        self.savepoints.set(self.savepoints.get() + 1);
     }
 
     /// Undo all operations in this transaction since the most recent call to [`set_savepoint`]
@@ -214,8 +270,44 @@ impl<'db, DB> Transaction<'db, DB> {
     pub fn rollback_to_savepoint(&self) -> Result<(), Error> {
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_rollback_to_savepoint(self.inner));
-            Ok(())
         }
+        self.savepoints.set(self.savepoints.get().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Returns the number of save points set with [`set_savepoint`] which haven't been removed
+    /// by [`rollback_to_savepoint`] or [`rollback`] yet.
+    ///
+    /// [`set_savepoint`]: Self::set_savepoint
+    /// [`rollback_to_savepoint`]: Self::rollback_to_savepoint
+    /// [`rollback`]: Self::rollback
+    pub fn num_savepoints(&self) -> usize {
+        self.savepoints.get()
+    }
+
+    /// Returns the number of puts pending in this transaction, in all column families.
+    ///
+    /// The counts are decoded from the pending writes (see [`to_bytes`]), which are serialized
+    /// again on every call, so this takes time proportional to their size. Returns `None` if the
+    /// pending writes contain a record which can't be decoded, such as a blob index or a
+    /// wide-column entity.
+    ///
+    /// [`to_bytes`]: Self::to_bytes
+    pub fn num_writes(&self) -> Option<usize> {
+        count_operations(&self.to_bytes()).map(|counts| counts.puts)
+    }
+
+    /// Returns the number of deletes pending in this transaction, in all column families.
+    /// Single deletes and delete ranges are counted as deletes too. See
+    /// [`num_writes`](Self::num_writes).
+    pub fn num_deletes(&self) -> Option<usize> {
+        count_operations(&self.to_bytes()).map(|counts| counts.deletes)
+    }
+
+    /// Returns the number of merges pending in this transaction, in all column families. See
+    /// [`num_writes`](Self::num_writes).
+    pub fn num_merges(&self) -> Option<usize> {
+        count_operations(&self.to_bytes()).map(|counts| counts.merges)
     }
 
     /// Get the bytes associated with a key value.
@@ -359,6 +451,7 @@ impl<'db, DB> Transaction<'db, DB> {
         key: K,
         readopts: &ReadOptions,
     ) -> Result<Option<DBPinnableSlice>, Error> {
+        check_not_dropped(cf)?;
         unsafe {
             let val = ffi_try!(ffi::rocksdb_transaction_get_pinned_cf(
                 self.inner,
@@ -426,7 +519,9 @@ impl<'db, DB> Transaction<'db, DB> {
     ///
     /// If this transaction was created by a [`TransactionDB`], it can return error of kind:
     /// * [`Busy`] if there is a write conflict.
-    /// * [`TimedOut`] if a lock could not be acquired.
+    /// * [`TimedOut`] if a lock could not be acquired within the lock timeout of the
+    ///   transaction (see [`TransactionOptions::set_lock_timeout`]), or the read didn't
+    ///   complete before the deadline of `opts` (see [`ReadOptions::set_deadline`]).
     /// * [`TryAgain`] if the memtable history size is not large enough.
     /// * [`MergeInProgress`] if merge operations cannot be resolved.
     /// * or other errors if this key could not be read.
@@ -441,6 +536,7 @@ impl<'db, DB> Transaction<'db, DB> {
     /// [`MergeInProgress`]: crate::ErrorKind::MergeInProgress
     /// [`TransactionDB`]: crate::TransactionDB
     /// [`OptimisticTransactionDB`]: crate::OptimisticTransactionDB
+    /// [`TransactionOptions::set_lock_timeout`]: crate::TransactionOptions::set_lock_timeout
     /// [`commit`]: Self::commit
     /// [`DB::get`]: crate::DB::get
     pub fn get_for_update_cf_opt<K: AsRef<[u8]>>(
@@ -461,6 +557,7 @@ impl<'db, DB> Transaction<'db, DB> {
         exclusive: bool,
         opts: &ReadOptions,
     ) -> Result<Option<DBPinnableSlice>, Error> {
+        check_not_dropped(cf)?;
         unsafe {
             let val = ffi_try!(ffi::rocksdb_transaction_get_pinned_for_update_cf(
                 self.inner,
@@ -478,6 +575,52 @@ impl<'db, DB> Transaction<'db, DB> {
         }
     }
 
+    /// Lock the key as [`get_for_update`] does, without copying its value.
+    ///
+    /// Returns whether the key exists. Conflict checking is the same as for
+    /// [`get_for_update`].
+    ///
+    /// [`get_for_update`]: Self::get_for_update
+    pub fn lock_key<K: AsRef<[u8]>>(&self, key: K, exclusive: bool) -> Result<bool, Error> {
+        self.get_pinned_for_update_opt(key, exclusive, &ReadOptions::default())
+            .map(|v| v.is_some())
+    }
+
+    /// Lock the key in the given column family as [`get_for_update_cf`] does, without copying
+    /// its value.
+    ///
+    /// Returns whether the key exists. Conflict checking is the same as for
+    /// [`get_for_update_cf`].
+    ///
+    /// [`get_for_update_cf`]: Self::get_for_update_cf
+    pub fn lock_key_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        exclusive: bool,
+    ) -> Result<bool, Error> {
+        self.get_pinned_for_update_cf_opt(cf, key, exclusive, &ReadOptions::default())
+            .map(|v| v.is_some())
+    }
+
+    /// Lock the given keys as [`get_for_update`] does, without returning their values.
+    ///
+    /// The keys are deduplicated and locked in ascending byte order, so transactions which
+    /// all lock their keys through this function can't deadlock on each other, whatever
+    /// order the keys are passed in. Stops at the first key which can't be locked and returns
+    /// its error; keys locked before that stay locked until the transaction ends.
+    ///
+    /// [`get_for_update`]: Self::get_for_update
+    pub fn lock_keys_ordered(&self, keys: &[&[u8]], exclusive: bool) -> Result<(), Error> {
+        let mut keys = keys.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        for key in keys {
+            self.lock_key(key, exclusive)?;
+        }
+        Ok(())
+    }
+
     /// Return the values associated with the given keys.
     pub fn multi_get<K, I>(&self, keys: I) -> Vec<Result<Option<Vec<u8>>, Error>>
     where
@@ -550,6 +693,12 @@ impl<'db, DB> Transaction<'db, DB> {
             .into_iter()
             .map(|(cf, key)| ((cf, Box::from(key.as_ref())), key.as_ref().len()))
             .unzip();
+        if cfs_and_keys.iter().any(|(cf, _)| cf.is_dropped()) {
+            return cfs_and_keys
+                .iter()
+                .map(|_| Err(column_family_dropped()))
+                .collect();
+        }
         let ptr_keys: Vec<_> = cfs_and_keys
             .iter()
             .map(|(_, k)| k.as_ptr() as *const c_char)
@@ -597,6 +746,26 @@ impl<'db, DB> Transaction<'db, DB> {
         }
     }
 
+    /// Put each of the key value pairs in default column family as [`put`] does.
+    ///
+    /// Stops at the first pair which can't be put and returns its error, of the same kind as
+    /// the one of [`put`]. The number `n` of pairs put before it is reported by appending
+    /// ` (after putting n pairs)` to the error message. Those pairs stay in the transaction.
+    ///
+    /// [`put`]: Self::put
+    pub fn put_batch<K, V, I>(&self, pairs: I) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (n, (key, value)) in pairs.into_iter().enumerate() {
+            self.put(key, value)
+                .map_err(|e| Error::new(format!("{} (after putting {} pairs)", e, n)))?;
+        }
+        Ok(())
+    }
+
     /// Put the key value in the given column famuly and do conflict checking on the key.
     ///
     /// If this transaction was created by a [`TransactionDB`], it can return error of kind:
@@ -618,6 +787,7 @@ impl<'db, DB> Transaction<'db, DB> {
         key: K,
         value: V,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_put_cf(
                 self.inner,
@@ -670,6 +840,7 @@ impl<'db, DB> Transaction<'db, DB> {
         key: K,
         value: V,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_merge_cf(
                 self.inner,
@@ -718,6 +889,7 @@ impl<'db, DB> Transaction<'db, DB> {
         cf: &impl AsColumnFamilyRef,
         key: K,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_delete_cf(
                 self.inner,
@@ -753,7 +925,7 @@ impl<'db, DB> Transaction<'db, DB> {
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -787,7 +959,7 @@ impl<'db, DB> Transaction<'db, DB> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -797,7 +969,7 @@ impl<'db, DB> Transaction<'db, DB> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -809,7 +981,7 @@ impl<'db, DB> Transaction<'db, DB> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
@@ -827,7 +999,7 @@ impl<'db, DB> Transaction<'db, DB> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -844,24 +1016,70 @@ impl<'db, DB> Transaction<'db, DB> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
     }
 
+    /// Returns a copy of the writes pending in this transaction.
+    ///
+    /// The returned batch can be inspected with [`WriteBatchWithTransaction::iterate`], e.g.
+    /// to validate or log the changes before committing. Later writes to the transaction are
+    /// not reflected in it.
     pub fn get_writebatch(&self) -> WriteBatchWithTransaction<true> {
+        WriteBatchWithTransaction::from_data(&self.to_bytes())
+    }
+
+    /// Serializes the writes pending in this transaction.
+    ///
+    /// The bytes are the write batch representation of RocksDB, so they can be shipped to
+    /// another process (e.g. a follower) and turned back into a batch with
+    /// [`WriteBatchWithTransaction::from_data`], to be applied with `DB::write`. Savepoints are
+    /// not part of the representation: only the writes which are currently pending are included.
+    pub fn to_bytes(&self) -> Vec<u8> {
         unsafe {
             let wi = ffi::rocksdb_transaction_get_writebatch_wi(self.inner);
-            let mut len: usize = 0;
-            let ptr = ffi::rocksdb_writebatch_wi_data(wi, &mut len as _);
-            let data = std::slice::from_raw_parts(ptr, len).to_owned();
-            let writebatch = ffi::rocksdb_writebatch_create_from(data.as_ptr(), data.len());
-            WriteBatchWithTransaction { inner: writebatch }
+            let mut len: size_t = 0;
+            let ptr = ffi::rocksdb_writebatch_wi_data(wi, &mut len);
+            let data = std::slice::from_raw_parts(ptr as *const u8, len as usize).to_vec();
+            // `wi` only wraps the batch owned by the transaction, so just the wrapper is freed.
+            ffi::rocksdb_free(wi as *mut c_void);
+            data
         }
     }
 
+    /// Returns the writes pending in this transaction, in the order they were made.
+    ///
+    /// Unlike [`iterator`](Self::iterator), the writes are not merged with the state of the DB:
+    /// only the keys written by this transaction are returned, along with the value of puts and
+    /// merges. The writes of all column families are returned.
+    ///
+    /// All pending writes are copied out of the transaction when this is called, so its cost
+    /// grows with the size of the transaction, and later writes to the transaction are not
+    /// reflected in the returned iterator.
+    ///
+    /// Returns a `NotSupported` error if the pending writes contain a record which can't be
+    /// decoded, such as a blob index or a wide-column entity.
+    pub fn pending_iterator(&self) -> Result<impl Iterator<Item = PendingWrite>, Error> {
+        let data = self.to_bytes();
+        let mut writes = Vec::new();
+        decode_operations(&data, |write_type, cf_id, key, value| {
+            writes.push(PendingWrite {
+                write_type,
+                cf_id,
+                key: Box::from(key),
+                value: value.map(Box::from),
+            });
+        })
+        .ok_or_else(|| {
+            Error::new("Not implemented: undecodable pending write of a transaction".to_owned())
+        })?;
+        Ok(writes.into_iter())
+    }
+
     pub fn rebuild_from_writebatch(
         &self,
         writebatch: &WriteBatchWithTransaction<true>,
     ) -> Result<(), Error> {
+        writebatch.check_cf_not_dropped()?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_rebuild_from_writebatch(
                 self.inner,
@@ -872,6 +1090,64 @@ impl<'db, DB> Transaction<'db, DB> {
     }
 }
 
+impl<'db, DB: DBAccess> Transaction<'db, DB> {
+    /// Get the bytes associated with a key value from the committed state of the DB,
+    /// ignoring the writes pending in this transaction.
+    ///
+    /// See [`get_committed_cf_opt`] for details.
+    ///
+    /// [`get_committed_cf_opt`]: Self::get_committed_cf_opt
+    pub fn get_committed<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, Error> {
+        self.get_committed_opt(key, &ReadOptions::default())
+    }
+
+    /// Get the bytes associated with a key value and the given column family from the
+    /// committed state of the DB, ignoring the writes pending in this transaction.
+    ///
+    /// See [`get_committed_cf_opt`] for details.
+    ///
+    /// [`get_committed_cf_opt`]: Self::get_committed_cf_opt
+    pub fn get_committed_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.get_committed_cf_opt(cf, key, &ReadOptions::default())
+    }
+
+    /// Get the bytes associated with a key value from the committed state of the DB
+    /// with read options, ignoring the writes pending in this transaction.
+    ///
+    /// See [`get_committed_cf_opt`] for details.
+    ///
+    /// [`get_committed_cf_opt`]: Self::get_committed_cf_opt
+    pub fn get_committed_opt<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        readopts: &ReadOptions,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.db.get_opt(key, readopts)
+    }
+
+    /// Get the bytes associated with a key value and the given column family from the
+    /// committed state of the DB with read options.
+    ///
+    /// Unlike [`get_cf_opt`], this reads the base DB the transaction was created from, so
+    /// it neither sees the writes pending in this transaction nor uses the transaction's
+    /// snapshot unless one is set in `readopts`. It doesn't track the key for conflict
+    /// checking either.
+    ///
+    /// [`get_cf_opt`]: Self::get_cf_opt
+    pub fn get_committed_cf_opt<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        readopts: &ReadOptions,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        self.db.get_cf_opt(cf, key, readopts)
+    }
+}
+
 impl<'db, DB> Drop for Transaction<'db, DB> {
     fn drop(&mut self) {
         unsafe {
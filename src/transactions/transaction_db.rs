@@ -14,18 +14,19 @@
 //
 
 use std::{
+    cell::Cell,
     collections::BTreeMap,
     ffi::CString,
     fs, iter,
-    marker::PhantomData,
     path::{Path, PathBuf},
     ptr,
     sync::{Arc, Mutex},
 };
 
 use crate::{
-    column_family::UnboundColumnFamily,
+    column_family::{check_not_dropped, column_family_dropped, UnboundColumnFamily},
     db::{convert_values, DBAccess},
+    db_lock::ProcessLock,
     db_options::OptionsMustOutliveDB,
     ffi,
     ffi_util::to_cpath,
@@ -75,6 +76,8 @@ pub struct TransactionDB<T: ThreadMode = DefaultThreadMode> {
     // prepared 2pc transactions.
     prepared: Mutex<Vec<*mut rocksdb_transaction_t>>,
     _outlive: Vec<OptionsMustOutliveDB>,
+    // Dropped last, once the database has been closed.
+    _lock: ProcessLock,
 }
 
 unsafe impl<T: ThreadMode> Send for TransactionDB<T> {}
@@ -238,6 +241,7 @@ impl<T: ThreadMode> TransactionDB<T> {
                 e
             )));
         }
+        let lock = ProcessLock::acquire(path.as_ref());
 
         let db: *mut ffi::rocksdb_transactiondb_t;
         let mut cf_map = BTreeMap::new();
@@ -289,7 +293,7 @@ impl<T: ThreadMode> TransactionDB<T> {
             }
 
             for (cf_desc, inner) in cfs_v.iter().zip(cfhandles) {
-                cf_map.insert(cf_desc.name.clone(), inner);
+                cf_map.insert(cf_desc.name.clone(), (inner, cf_desc.options.comparator));
             }
         }
 
@@ -314,6 +318,7 @@ impl<T: ThreadMode> TransactionDB<T> {
             path: path.as_ref().to_path_buf(),
             prepared: Mutex::new(prepared),
             _outlive: outlive,
+            _lock: lock,
         })
     }
 
@@ -398,6 +403,9 @@ impl<T: ThreadMode> TransactionDB<T> {
     }
 
     /// Creates a transaction with options.
+    ///
+    /// `write_opts` are used when the transaction is committed, e.g. to sync the commit to
+    /// disk.
     pub fn transaction_opt<'a>(
         &'a self,
         write_opts: &WriteOptions,
@@ -412,10 +420,61 @@ impl<T: ThreadMode> TransactionDB<T> {
                     std::ptr::null_mut(),
                 )
             },
-            _marker: PhantomData::default(),
+            savepoints: Cell::new(0),
+            db: self,
         }
     }
 
+    /// Begins a new transaction with default options, reusing the allocation of `txn`.
+    ///
+    /// See [`reuse_transaction_opt`](Self::reuse_transaction_opt).
+    pub fn reuse_transaction<'a>(
+        &'a self,
+        txn: Transaction<'a, Self>,
+    ) -> Result<Transaction<'a, Self>, Error> {
+        self.reuse_transaction_opt(
+            txn,
+            &WriteOptions::default(),
+            &TransactionOptions::default(),
+        )
+    }
+
+    /// Begins a new transaction with options, reusing the allocation of `txn`.
+    ///
+    /// This avoids allocating a new transaction each time when transactions are created in a
+    /// tight loop. Any pending writes of `txn` are discarded and its locks are released, so the
+    /// returned transaction starts empty, without savepoints.
+    ///
+    /// Returns an `InvalidArgument` error if `txn` was created by another `TransactionDB`, in
+    /// which case `txn` is dropped as usual.
+    pub fn reuse_transaction_opt<'a>(
+        &'a self,
+        txn: Transaction<'a, Self>,
+        write_opts: &WriteOptions,
+        txn_opts: &TransactionOptions,
+    ) -> Result<Transaction<'a, Self>, Error> {
+        if !ptr::eq(txn.db, self) {
+            return Err(Error::new(
+                "Invalid argument: transaction belongs to a different TransactionDB".to_owned(),
+            ));
+        }
+        let old_txn = txn.inner;
+        // The handle is reinitialized in place below, so it must not be destroyed.
+        std::mem::forget(txn);
+        Ok(Transaction {
+            inner: unsafe {
+                ffi::rocksdb_transaction_begin(
+                    self.inner,
+                    write_opts.inner,
+                    txn_opts.inner,
+                    old_txn,
+                )
+            },
+            savepoints: Cell::new(0),
+            db: self,
+        })
+    }
+
     /// Get all prepared transactions for recovery.
     ///
     /// This function is expected to call once after open database.
@@ -427,7 +486,8 @@ impl<T: ThreadMode> TransactionDB<T> {
             .drain(0..)
             .map(|inner| Transaction {
                 inner,
-                _marker: PhantomData::default(),
+                savepoints: Cell::new(0),
+                db: self,
             })
             .collect()
     }
@@ -510,6 +570,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         key: K,
         readopts: &ReadOptions,
     ) -> Result<Option<DBPinnableSlice>, Error> {
+        check_not_dropped(cf)?;
         unsafe {
             let val = ffi_try!(ffi::rocksdb_transactiondb_get_pinned_cf(
                 self.inner,
@@ -598,6 +659,12 @@ impl<T: ThreadMode> TransactionDB<T> {
             .into_iter()
             .map(|(cf, key)| ((cf, Box::from(key.as_ref())), key.as_ref().len()))
             .unzip();
+        if cfs_and_keys.iter().any(|(cf, _)| cf.is_dropped()) {
+            return cfs_and_keys
+                .iter()
+                .map(|_| Err(column_family_dropped()))
+                .collect();
+        }
         let ptr_keys: Vec<_> = cfs_and_keys
             .iter()
             .map(|(_, k)| k.as_ptr() as *const c_char)
@@ -672,6 +739,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_put_cf(
                 self.inner,
@@ -695,6 +763,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         batch: WriteBatchWithTransaction<true>,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        batch.check_cf_not_dropped()?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_write(
                 self.inner,
@@ -750,6 +819,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_merge_cf(
                 self.inner,
@@ -798,6 +868,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         key: K,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        check_not_dropped(cf)?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_delete_cf(
                 self.inner,
@@ -834,7 +905,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -868,7 +939,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -878,7 +949,7 @@ impl<T: ThreadMode> TransactionDB<T> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -890,7 +961,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
@@ -908,7 +979,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -925,7 +996,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
     }
 
     pub fn snapshot(&self) -> SnapshotWithThreadMode<Self> {
@@ -937,9 +1008,13 @@ impl TransactionDB<SingleThreaded> {
     /// Creates column family with given name and options.
     pub fn create_cf<N: AsRef<str>>(&mut self, name: N, opts: &Options) -> Result<(), Error> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
-        self.cfs
-            .cfs
-            .insert(name.as_ref().to_string(), ColumnFamily { inner });
+        self.cfs.cfs.insert(
+            name.as_ref().to_string(),
+            ColumnFamily {
+                inner,
+                comparator: opts.comparator,
+            },
+        );
         Ok(())
     }
 
@@ -955,7 +1030,7 @@ impl TransactionDB<MultiThreaded> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
         self.cfs.cfs.write().unwrap().insert(
             name.as_ref().to_string(),
-            Arc::new(UnboundColumnFamily { inner }),
+            Arc::new(UnboundColumnFamily::new(inner, opts.comparator)),
         );
         Ok(())
     }
@@ -12,7 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{ffi, AsColumnFamilyRef};
+use crate::{
+    column_family::{check_range, column_family_dropped, inverted_range},
+    comparator::CompareFn,
+    ffi, AsColumnFamilyRef, Error,
+};
 use libc::{c_char, c_void, size_t};
 use std::slice;
 
@@ -23,6 +27,10 @@ pub type WriteBatch = WriteBatchWithTransaction<false>;
 ///
 /// [`delete_range`] is not supported in [`Transaction`].
 ///
+/// Operations on a column family which was already dropped aren't added to the batch;
+/// writing the batch then fails with a
+/// [`ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped) error.
+///
 /// Making an atomic commit of several writes:
 ///
 /// ```
@@ -48,6 +56,15 @@ pub type WriteBatch = WriteBatchWithTransaction<false>;
 /// [`Transaction`]: crate::Transaction
 pub struct WriteBatchWithTransaction<const TRANSACTION: bool> {
     pub(crate) inner: *mut ffi::rocksdb_writebatch_t,
+    // Set when an operation was added for a column family which was already dropped. Such
+    // operations are left out of the batch, and writing the batch fails instead.
+    cf_dropped: bool,
+    // Set when a range deletion with a start key after its end key was added for a column
+    // family. Such deletions are left out of the batch, and writing the batch fails instead.
+    inverted_range: bool,
+    // The bounds of the range deletions of the default column family, which are checked with
+    // its comparator once the batch is written.
+    default_cf_ranges: Vec<(Box<[u8]>, Box<[u8]>)>,
 }
 
 /// Receives the puts and deletes of a write batch.
@@ -61,6 +78,18 @@ pub trait WriteBatchIterator {
     fn delete(&mut self, key: Box<[u8]>);
 }
 
+/// The kind of an operation in a write batch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WriteType {
+    Put,
+    Merge,
+    Delete,
+    SingleDelete,
+    /// Deletion of the keys from a begin key (inclusive) to an end key (exclusive).
+    DeleteRange,
+}
+
 unsafe extern "C" fn writebatch_put_callback(
     state: *mut c_void,
     k: *const c_char,
@@ -151,6 +180,10 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        if cf.is_dropped() {
+            self.cf_dropped = true;
+            return;
+        }
         let key = key.as_ref();
         let value = value.as_ref();
 
@@ -190,6 +223,10 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        if cf.is_dropped() {
+            self.cf_dropped = true;
+            return;
+        }
         let key = key.as_ref();
         let value = value.as_ref();
 
@@ -219,6 +256,10 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
     }
 
     pub fn delete_cf<K: AsRef<[u8]>>(&mut self, cf: &impl AsColumnFamilyRef, key: K) {
+        if cf.is_dropped() {
+            self.cf_dropped = true;
+            return;
+        }
         let key = key.as_ref();
 
         unsafe {
@@ -236,17 +277,207 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
         unsafe {
             ffi::rocksdb_writebatch_clear(self.inner);
         }
+        self.cf_dropped = false;
+        self.inverted_range = false;
+        self.default_cf_ranges.clear();
+    }
+
+    /// Append the operations of `other` to this batch, after the operations already in it.
+    ///
+    /// The C API has no direct way to do this, so the batch is rebuilt from the concatenation
+    /// of both serialized batches. Save points set on this batch are lost.
+    pub fn append(&mut self, other: &Self) {
+        self.cf_dropped |= other.cf_dropped;
+        self.inverted_range |= other.inverted_range;
+        self.default_cf_ranges
+            .extend(other.default_cf_ranges.iter().cloned());
+        let other = other.data();
+        if other.len() <= WRITE_BATCH_HEADER_SIZE {
+            return;
+        }
+
+        let mut rep = self.data().to_vec();
+        rep.extend_from_slice(&other[WRITE_BATCH_HEADER_SIZE..]);
+        let count = self.len() as u32 + decode_count(other);
+        rep[8..WRITE_BATCH_HEADER_SIZE].copy_from_slice(&count.to_le_bytes());
+
+        unsafe {
+            let inner =
+                ffi::rocksdb_writebatch_create_from(rep.as_ptr() as *const c_char, rep.len());
+            ffi::rocksdb_writebatch_destroy(self.inner);
+            self.inner = inner;
+        }
+    }
+
+    /// Creates a batch from its serialized representation, e.g. as returned by
+    /// [`data`](Self::data) or [`Transaction::to_bytes`](crate::Transaction::to_bytes).
+    ///
+    /// The bytes are copied, so `data` doesn't need to outlive the batch.
+    pub fn from_data(data: &[u8]) -> Self {
+        Self::from_inner(unsafe {
+            ffi::rocksdb_writebatch_create_from(data.as_ptr() as *const c_char, data.len())
+        })
+    }
+
+    pub(crate) fn from_inner(inner: *mut ffi::rocksdb_writebatch_t) -> Self {
+        Self {
+            inner,
+            cf_dropped: false,
+            inverted_range: false,
+            default_cf_ranges: Vec::new(),
+        }
+    }
+
+    /// Fails with a [`ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped) error if
+    /// an operation was added for a column family which was dropped at that point.
+    pub(crate) fn check_cf_not_dropped(&self) -> Result<(), Error> {
+        if self.cf_dropped {
+            Err(column_family_dropped())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fails with an `InvalidArgument` error if a range deletion was added whose start key sorts
+    /// after its end key, using `default_comparator` for the default column family.
+    pub(crate) fn check_ranges(&self, default_comparator: Option<CompareFn>) -> Result<(), Error> {
+        if self.inverted_range {
+            return Err(inverted_range());
+        }
+        for (from, to) in &self.default_cf_ranges {
+            check_range(default_comparator, from, to)?;
+        }
+        Ok(())
+    }
+
+    /// The serialized representation of this batch.
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            let mut size: size_t = 0;
+            let rep = ffi::rocksdb_writebatch_data(self.inner, &mut size);
+            slice::from_raw_parts(rep as *const u8, size as usize)
+        }
     }
 }
 
+/// Size of the header of a serialized write batch: an 8 byte sequence number followed by a
+/// 4 byte operation count, both little-endian.
+const WRITE_BATCH_HEADER_SIZE: usize = 12;
+
+fn decode_count(rep: &[u8]) -> u32 {
+    let mut count = [0; 4];
+    count.copy_from_slice(&rep[8..WRITE_BATCH_HEADER_SIZE]);
+    u32::from_le_bytes(count)
+}
+
+/// Numbers of the operations of a serialized write batch, by kind.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct OperationCounts {
+    pub puts: usize,
+    pub deletes: usize,
+    pub merges: usize,
+}
+
+/// Counts the operations of a serialized write batch, as the C API doesn't expose the counters
+/// of `WriteBatch`. Single deletes and delete ranges are counted as deletes.
+///
+/// Returns `None` if the batch can't be decoded, see [`decode_operations`], so partial counts
+/// are never returned.
+pub(crate) fn count_operations(rep: &[u8]) -> Option<OperationCounts> {
+    let mut counts = OperationCounts::default();
+    decode_operations(rep, |write_type, _, _, _| match write_type {
+        WriteType::Put => counts.puts += 1,
+        WriteType::Merge => counts.merges += 1,
+        WriteType::Delete | WriteType::SingleDelete | WriteType::DeleteRange => counts.deletes += 1,
+    })?;
+    Some(counts)
+}
+
+/// Calls `f` with the type, column family ID, key and value of each operation of a serialized
+/// write batch, in order. Delete ranges are passed their begin key as key and their end key as
+/// value.
+///
+/// Returns `None` if the batch contains a record which isn't decoded here (such as blob indexes
+/// or wide-column entities), or if the decoded operations don't add up to the count in the
+/// header.
+pub(crate) fn decode_operations<'a>(
+    rep: &'a [u8],
+    mut f: impl FnMut(WriteType, u32, &'a [u8], Option<&'a [u8]>),
+) -> Option<()> {
+    let mut input = rep.get(WRITE_BATCH_HEADER_SIZE..)?;
+    let mut count = 0;
+    while let Some((&tag, rest)) = input.split_first() {
+        input = rest;
+        // The record tags are the `ValueType`s of db/dbformat.h. The second tag of each pair is
+        // the variant for a column family other than the default one.
+        let (write_type, has_cf, has_value) = match tag {
+            0x1 | 0x5 => (WriteType::Put, tag == 0x5, true),
+            0x2 | 0x6 => (WriteType::Merge, tag == 0x6, true),
+            0x0 | 0x4 => (WriteType::Delete, tag == 0x4, false),
+            0x7 | 0x8 => (WriteType::SingleDelete, tag == 0x8, false),
+            0xF | 0xE => (WriteType::DeleteRange, tag == 0xE, true),
+            // Log data, and the end, commit and rollback markers of two-phase commit.
+            0x3 | 0xA | 0xB | 0xC => {
+                decode_slice(&mut input)?;
+                continue;
+            }
+            // Noop, and the begin markers of two-phase commit.
+            0xD | 0x9 | 0x12 | 0x13 => continue,
+            _ => return None,
+        };
+        let cf_id = if has_cf {
+            decode_varint32(&mut input)?
+        } else {
+            0
+        };
+        let key = decode_slice(&mut input)?;
+        let value = if has_value {
+            Some(decode_slice(&mut input)?)
+        } else {
+            None
+        };
+        f(write_type, cf_id, key, value);
+        count += 1;
+    }
+    if count != decode_count(rep) {
+        return None;
+    }
+    Some(())
+}
+
+/// Decodes a length-prefixed slice.
+fn decode_slice<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = decode_varint32(input)? as usize;
+    let slice = input.get(..len)?;
+    *input = &input[len..];
+    Some(slice)
+}
+
+fn decode_varint32(input: &mut &[u8]) -> Option<u32> {
+    let mut value = 0;
+    for (i, &byte) in input.iter().enumerate().take(5) {
+        value |= u32::from(byte & 0x7F) << (7 * i);
+        if byte & 0x80 == 0 {
+            *input = &input[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
 impl WriteBatchWithTransaction<false> {
     /// Remove database entries from start key to end key.
     ///
     /// Removes the database entries in the range ["begin_key", "end_key"), i.e.,
     /// including "begin_key" and excluding "end_key". It is not an error if no
     /// keys exist in the range ["begin_key", "end_key").
+    ///
+    /// If "begin_key" sorts after "end_key" according to the comparator of the default column
+    /// family, writing the batch fails with an `InvalidArgument` error.
     pub fn delete_range<K: AsRef<[u8]>>(&mut self, from: K, to: K) {
         let (start_key, end_key) = (from.as_ref(), to.as_ref());
+        self.default_cf_ranges
+            .push((Box::from(start_key), Box::from(end_key)));
 
         unsafe {
             ffi::rocksdb_writebatch_delete_range(
@@ -264,8 +495,19 @@ impl WriteBatchWithTransaction<false> {
     /// Removes the database entries in the range ["begin_key", "end_key"), i.e.,
     /// including "begin_key" and excluding "end_key". It is not an error if no
     /// keys exist in the range ["begin_key", "end_key").
+    ///
+    /// If "begin_key" sorts after "end_key" according to the comparator of the column family,
+    /// the deletion is left out and writing the batch fails with an `InvalidArgument` error.
     pub fn delete_range_cf<K: AsRef<[u8]>>(&mut self, cf: &impl AsColumnFamilyRef, from: K, to: K) {
+        if cf.is_dropped() {
+            self.cf_dropped = true;
+            return;
+        }
         let (start_key, end_key) = (from.as_ref(), to.as_ref());
+        if check_range(cf.comparator(), start_key, end_key).is_err() {
+            self.inverted_range = true;
+            return;
+        }
 
         unsafe {
             ffi::rocksdb_writebatch_delete_range_cf(
@@ -282,9 +524,7 @@ impl WriteBatchWithTransaction<false> {
 
 impl<const TRANSACTION: bool> Default for WriteBatchWithTransaction<TRANSACTION> {
     fn default() -> Self {
-        Self {
-            inner: unsafe { ffi::rocksdb_writebatch_create() },
-        }
+        Self::from_inner(unsafe { ffi::rocksdb_writebatch_create() })
     }
 }
 
@@ -297,3 +537,79 @@ impl<const TRANSACTION: bool> Drop for WriteBatchWithTransaction<TRANSACTION> {
 }
 
 unsafe impl<const TRANSACTION: bool> Send for WriteBatchWithTransaction<TRANSACTION> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch_rep(count: u32, records: &[&[u8]]) -> Vec<u8> {
+        let mut rep = vec![0; 8];
+        rep.extend_from_slice(&count.to_le_bytes());
+        for record in records {
+            rep.extend_from_slice(record);
+        }
+        rep
+    }
+
+    #[test]
+    fn count_operations_two_phase_commit_markers() {
+        let rep = batch_rep(
+            3,
+            &[
+                // Begin persisted prepare, as written by the write prepared policy.
+                &[0x12],
+                &[0x1, 1, b'a', 1, b'1'],
+                &[0x6, 3, 1, b'b', 1, b'2'],
+                &[0xA, 3, b'x', b'i', b'd'],
+                // Begin unprepare, as written by the write unprepared policy.
+                &[0x13],
+                &[0x4, 3, 1, b'c'],
+                &[0xA, 3, b'x', b'i', b'd'],
+                &[0xB, 3, b'x', b'i', b'd'],
+            ],
+        );
+        assert_eq!(
+            count_operations(&rep),
+            Some(OperationCounts {
+                puts: 1,
+                deletes: 1,
+                merges: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_operations_reports_column_families() {
+        let rep = batch_rep(
+            3,
+            &[
+                &[0x1, 1, b'a', 1, b'1'],
+                &[0x6, 3, 1, b'b', 1, b'2'],
+                &[0xE, 200, 1, 1, b'c', 1, b'd'],
+            ],
+        );
+        let mut operations = Vec::new();
+        decode_operations(&rep, |write_type, cf_id, key, value| {
+            operations.push((write_type, cf_id, key, value))
+        })
+        .unwrap();
+        assert_eq!(
+            operations,
+            vec![
+                (WriteType::Put, 0, &b"a"[..], Some(&b"1"[..])),
+                (WriteType::Merge, 3, &b"b"[..], Some(&b"2"[..])),
+                (WriteType::DeleteRange, 200, &b"c"[..], Some(&b"d"[..])),
+            ]
+        );
+    }
+
+    #[test]
+    fn count_operations_rejects_unknown_records() {
+        // A blob index, which isn't decoded.
+        let rep = batch_rep(1, &[&[0x11, 1, b'a', 1, b'1']]);
+        assert_eq!(count_operations(&rep), None);
+        // A count in the header which doesn't match the records.
+        let rep = batch_rep(2, &[&[0x1, 1, b'a', 1, b'1']]);
+        assert_eq!(count_operations(&rep), None);
+    }
+}
@@ -0,0 +1,9 @@
+use rocksdb::{TransactionDB, SingleThreaded};
+
+fn main() {
+    let db = TransactionDB::<SingleThreaded>::open_default("foo").unwrap();
+    let _value = {
+        let txn = db.transaction();
+        txn.get_pinned(b"k1").unwrap()
+    };
+}
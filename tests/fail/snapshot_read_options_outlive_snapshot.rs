@@ -0,0 +1,9 @@
+use rocksdb::{ReadOptions, DB};
+
+fn main() {
+    let db = DB::open_default("foo").unwrap();
+    let _readopts = {
+        let snapshot = db.snapshot();
+        ReadOptions::default().with_snapshot(&snapshot)
+    };
+}
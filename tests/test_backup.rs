@@ -18,7 +18,7 @@ use pretty_assertions::assert_eq;
 
 use rocksdb::{
     backup::{BackupEngine, BackupEngineOptions, RestoreOptions},
-    DB,
+    WriteOptions, DB,
 };
 use util::DBPath;
 
@@ -103,3 +103,59 @@ fn restore_from_backup() {
         }
     }
 }
+
+#[test]
+fn backup_with_flush() {
+    let path = DBPath::new("backup_with_flush_test");
+    let restore_path = DBPath::new("backup_with_flush_restore_path");
+    {
+        let db = DB::open_default(&path).unwrap();
+        // Writes which skip the WAL only survive in a backup if the memtable is flushed first.
+        let mut write_opts = WriteOptions::default();
+        write_opts.disable_wal(true);
+        db.put_opt(b"k1", b"v1111", &write_opts).unwrap();
+
+        let backup_path = DBPath::new("backup_path_3");
+        let backup_opts = BackupEngineOptions::default();
+        let mut backup_engine = BackupEngine::open(&backup_opts, &backup_path).unwrap();
+        backup_engine.create_new_backup_flush(&db, true).unwrap();
+
+        let mut restore_option = RestoreOptions::default();
+        restore_option.set_keep_log_files(false);
+        backup_engine
+            .restore_from_latest_backup(&restore_path, &restore_path, &restore_option)
+            .unwrap();
+
+        let db_restore = DB::open_default(&restore_path).unwrap();
+        assert_eq!(db_restore.get(b"k1").unwrap().unwrap(), b"v1111");
+    }
+}
+
+#[test]
+fn backup_info_of_multiple_backups() {
+    let path = DBPath::new("backup_info_of_multiple_backups_test");
+    {
+        let db = DB::open_default(&path).unwrap();
+        let backup_path = DBPath::new("backup_path_4");
+        let backup_opts = BackupEngineOptions::default();
+        let mut backup_engine = BackupEngine::open(&backup_opts, &backup_path).unwrap();
+
+        db.put(b"k1", vec![b'x'; 4096]).unwrap();
+        backup_engine.create_new_backup_flush(&db, true).unwrap();
+        db.put(b"k2", vec![b'x'; 4096]).unwrap();
+        backup_engine.create_new_backup_flush(&db, true).unwrap();
+
+        let info = backup_engine.get_backup_info();
+        assert_eq!(info.len(), 2);
+        assert!(info[0].backup_id < info[1].backup_id);
+        assert!(info[0].timestamp <= info[1].timestamp);
+        for i in &info {
+            assert!(i.timestamp > 0);
+            assert!(i.size > 0);
+            assert!(i.num_files > 0);
+            assert!(backup_engine.verify_backup(i.backup_id).is_ok());
+        }
+        // The second backup contains one more table file than the first.
+        assert!(info[1].num_files > info[0].num_files);
+    }
+}
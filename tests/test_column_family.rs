@@ -16,7 +16,11 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{ColumnFamilyDescriptor, MergeOperands, Options, DB, DEFAULT_COLUMN_FAMILY_NAME};
+use rocksdb::{
+    BatchWriter, BlockBasedOptions, Cache, ColumnFamilyDescriptor, DBWithThreadMode, ErrorKind,
+    IteratorMode, MergeOperands, MultiThreaded, Options, WriteBatch, DB,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
 use util::DBPath;
 
 use std::fs;
@@ -263,6 +267,48 @@ fn test_column_family_with_options() {
     }
 }
 
+#[test]
+fn test_column_family_with_own_block_cache() {
+    let path = DBPath::new("_rust_rocksdb_cf_with_own_block_cache");
+
+    let hot_cache = Cache::new_lru_cache(8 << 20).unwrap();
+    let cold_cache = Cache::new_lru_cache(64 << 10).unwrap();
+    let cf_opts = |cache: &Cache| {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(cache);
+        let mut opts = Options::default();
+        opts.set_block_based_table_factory(&block_opts);
+        opts
+    };
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    #[cfg(feature = "multi-threaded-cf")]
+    let db = DB::open(&opts, &path).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut db = DB::open(&opts, &path).unwrap();
+    db.create_cf("hot", &cf_opts(&hot_cache)).unwrap();
+    db.create_cf("cold", &cf_opts(&cold_cache)).unwrap();
+
+    let hot = db.cf_handle("hot").unwrap();
+    let cold = db.cf_handle("cold").unwrap();
+    db.put_cf(&hot, b"k1", b"v1").unwrap();
+    db.put_cf(&cold, b"k1", b"v1").unwrap();
+    db.flush_cf(&hot).unwrap();
+    db.flush_cf(&cold).unwrap();
+
+    // Reading from one column family only fills the cache assigned to it.
+    let (hot_usage, cold_usage) = (hot_cache.get_usage(), cold_cache.get_usage());
+    assert_eq!(db.get_cf(&hot, b"k1").unwrap().unwrap(), b"v1");
+    assert!(hot_cache.get_usage() > hot_usage);
+    assert_eq!(cold_cache.get_usage(), cold_usage);
+
+    let hot_usage = hot_cache.get_usage();
+    assert_eq!(db.get_cf(&cold, b"k1").unwrap().unwrap(), b"v1");
+    assert!(cold_cache.get_usage() > cold_usage);
+    assert_eq!(hot_cache.get_usage(), hot_usage);
+}
+
 #[test]
 fn test_create_duplicate_column_family() {
     let n = DBPath::new("_rust_rocksdb_create_duplicate_column_family");
@@ -345,3 +391,156 @@ fn test_no_leaked_column_family() {
         drop(db);
     }
 }
+
+#[test]
+fn test_cf_handle_by_name() {
+    let path = DBPath::new("_rust_rocksdb_cf_handle_by_name");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    #[cfg(feature = "multi-threaded-cf")]
+    let db = DB::open(&opts, &path).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut db = DB::open(&opts, &path).unwrap();
+    db.create_cf("cf1", &opts).unwrap();
+    db.create_cf("cf2", &opts).unwrap();
+    db.drop_cf("cf2").unwrap();
+
+    let cf1 = db.cf_handle("cf1").unwrap();
+    db.put_cf(&cf1, b"k1", b"v1").unwrap();
+    assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+    assert!(db.cf_handle("cf2").is_none());
+    assert!(db.cf_handle("cf3").is_none());
+}
+
+#[test]
+fn test_dropped_column_family_handle() {
+    let path = DBPath::new("_rust_rocksdb_dropped_column_family_handle");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DBWithThreadMode::<MultiThreaded>::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    db.put_cf(&cf1, b"k1", b"v1").unwrap();
+
+    db.drop_cf("cf1").unwrap();
+    assert!(db.cf_handle("cf1").is_none());
+
+    let err = db.get_cf(&cf1, b"k1").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    let err = db.put_cf(&cf1, b"k2", b"v2").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    let err = db.delete_cf(&cf1, b"k1").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    let err = db.flush_cf(&cf1).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    let results = db.multi_get_cf(vec![(&cf1, b"k1")]);
+    assert_eq!(
+        results[0].as_ref().unwrap_err().kind(),
+        ErrorKind::ColumnFamilyDropped
+    );
+
+    let mut iter = db.iterator_cf(&cf1, IteratorMode::Start);
+    let err = iter.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    assert!(iter.next().is_none());
+    let mut raw = db.raw_iterator_cf(&cf1);
+    raw.seek_to_first();
+    assert!(!raw.valid());
+    assert_eq!(
+        raw.status().unwrap_err().kind(),
+        ErrorKind::ColumnFamilyDropped
+    );
+
+    // The write of the whole batch fails, including the operation on the default column family.
+    let mut batch = WriteBatch::default();
+    batch.put(b"k0", b"v0");
+    batch.put_cf(&cf1, b"k2", b"v2");
+    let err = db.write(batch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    assert!(db.get(b"k0").unwrap().is_none());
+
+    // A writer whose batch only held writes to the dropped column family must not succeed.
+    let mut writer = BatchWriter::new(&db, 4 << 20, 100);
+    writer.put_cf(&cf1, b"k2", b"v2").unwrap();
+    writer.delete_cf(&cf1, b"k1").unwrap();
+    let err = writer.finish().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+
+    let mut writer = BatchWriter::new(&db, 4 << 20, 1);
+    writer.merge_cf(&cf1, b"k2", b"v2").unwrap();
+    let err = writer.put(b"k0", b"v0").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    assert!(db.get(b"k0").unwrap().is_none());
+    writer.put(b"k0", b"v0").unwrap();
+    writer.finish().unwrap();
+    assert_eq!(db.get(b"k0").unwrap().unwrap(), b"v0");
+}
+
+#[test]
+fn test_open_cf_descriptors_create_missing() {
+    let path = DBPath::new("_rust_rocksdb_open_cf_descriptors_create_missing");
+    let descriptors = || {
+        ["cf1", "cf2", "cf3"]
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()))
+    };
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    {
+        let db = DB::open_cf_descriptors_create_missing(&opts, &path, descriptors()).unwrap();
+        for name in &["cf1", "cf2", "cf3"] {
+            let cf = db.cf_handle(name).unwrap();
+            db.put_cf(&cf, b"k1", name.as_bytes()).unwrap();
+        }
+    }
+
+    let mut cfs = DB::list_cf(&Options::default(), &path).unwrap();
+    cfs.sort();
+    assert_eq!(cfs, vec!["cf1", "cf2", "cf3", DEFAULT_COLUMN_FAMILY_NAME]);
+
+    {
+        let db = DB::open_cf_descriptors_create_missing(&opts, &path, descriptors()).unwrap();
+        for name in &["cf1", "cf2", "cf3"] {
+            let cf = db.cf_handle(name).unwrap();
+            assert_eq!(db.get_cf(&cf, b"k1").unwrap().unwrap(), name.as_bytes());
+        }
+    }
+}
+
+#[test]
+fn test_open_cf_descriptors_omitting_cf() {
+    let path = DBPath::new("_rust_rocksdb_open_cf_descriptors_omitting_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    {
+        let _db = DB::open_cf(&opts, &path, &["cf1", "cf2", "cf3"]).unwrap();
+    }
+
+    // The error names the column family which exists in the DB but wasn't passed.
+    let descriptors = ["cf1", "cf3"]
+        .iter()
+        .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+    let err = DB::open_cf_descriptors(&opts, &path, descriptors).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let message = err.to_string();
+    assert!(message.contains("cf2"), "{}", message);
+    assert!(!message.contains("cf1"), "{}", message);
+    assert!(!message.contains("cf3"), "{}", message);
+}
+
+#[test]
+fn test_column_family_descriptor_accessors() {
+    let mut opts = Options::default();
+    opts.enable_statistics();
+    let descriptor = ColumnFamilyDescriptor::new("cf1", opts);
+    assert_eq!(descriptor.name(), "cf1");
+    assert!(descriptor.options().get_statistics().is_some());
+
+    let descriptor = ColumnFamilyDescriptor::new("cf2", Options::default());
+    assert_eq!(descriptor.name(), "cf2");
+    assert!(descriptor.options().get_statistics().is_none());
+}
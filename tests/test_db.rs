@@ -19,11 +19,12 @@ use std::{mem, sync::Arc, thread, time::Duration};
 use pretty_assertions::assert_eq;
 
 use rocksdb::{
-    perf::get_memory_usage_stats, BlockBasedOptions, BottommostLevelCompaction, Cache,
+    perf::get_memory_usage_stats, properties, BlockBasedOptions, BottommostLevelCompaction, Cache,
     ColumnFamilyDescriptor, CompactOptions, CuckooTableOptions, DBAccess, DBCompactionStyle,
-    DBWithThreadMode, Env, Error, ErrorKind, FifoCompactOptions, IteratorMode, MultiThreaded,
-    Options, PerfContext, PerfMetric, ReadOptions, SingleThreaded, SliceTransform, Snapshot,
-    UniversalCompactOptions, UniversalCompactionStopStyle, WriteBatch, DB,
+    DBCompressionType, DBWithThreadMode, Direction, Env, Error, ErrorKind, FifoCompactOptions,
+    IteratorMode, MergeOperands, MultiThreaded, OpenBuilder, Options, PerfContext, PerfMetric,
+    ReadOptions, ReadTier, SingleThreaded, SliceTransform, Snapshot, UniversalCompactOptions,
+    UniversalCompactionStopStyle, WriteBatch, WriteOptions, DB,
 };
 use util::{assert_iter, pair, DBPath};
 
@@ -80,6 +81,27 @@ fn errors_do_stuff() {
     }
 }
 
+#[test]
+fn is_locked_works() {
+    let path = DBPath::new("_rust_rocksdb_is_locked");
+    let unused = DBPath::new("_rust_rocksdb_is_locked_unused");
+    {
+        let _db = DB::open_default(&path).unwrap();
+        assert!(DB::is_locked(&path));
+        assert!(!DB::is_locked(&unused));
+        // Checking doesn't create anything.
+        let unused_path: &std::path::Path = (&unused).as_ref();
+        assert!(!unused_path.exists());
+    }
+    // Once the DB is closed, the lock is released again.
+    assert!(!DB::is_locked(&path));
+    {
+        // Read-only instances don't take the lock.
+        let _db = DB::open_for_read_only(&Options::default(), &path, false).unwrap();
+        assert!(!DB::is_locked(&path));
+    }
+}
+
 #[test]
 fn writebatch_works() {
     let path = DBPath::new("_rust_rocksdb_writebacktest");
@@ -385,6 +407,86 @@ fn test_sequence_number() {
     }
 }
 
+#[test]
+fn test_approximate_sizes_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_approximate_sizes_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut cf_opts = Options::default();
+        cf_opts.set_compression_type(DBCompressionType::None);
+        let cfs = vec![ColumnFamilyDescriptor::new("cf1", cf_opts)];
+        let db = DB::open_cf_descriptors(&opts, &path, cfs).unwrap();
+        let cf = db.cf_handle("cf1").unwrap();
+
+        let value = vec![b'x'; 1024];
+        for i in 0..100_u32 {
+            db.put_cf(&cf, [&b"a"[..], &i.to_be_bytes()].concat(), &value)
+                .unwrap();
+        }
+        for i in 0..1000_u32 {
+            db.put_cf(&cf, [&b"b"[..], &i.to_be_bytes()].concat(), &value)
+                .unwrap();
+        }
+        db.flush_cf(&cf).unwrap();
+
+        let ranges: [(&[u8], &[u8]); 3] = [(b"a", b"b"), (b"b", b"c"), (b"y", b"z")];
+        let sizes = db.approximate_sizes_cf(&cf, &ranges).unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert!(sizes[0] > 0, "{:?}", sizes);
+        assert!(sizes[1] > 5 * sizes[0], "{:?}", sizes);
+        assert_eq!(sizes[2], 0, "{:?}", sizes);
+    }
+}
+
+#[test]
+fn test_count_keys_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_count_keys_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf = db.cf_handle("cf1").unwrap();
+
+        assert_eq!(db.count_keys_cf(&cf, true).unwrap(), 0);
+        assert_eq!(db.count_keys_cf(&cf, false).unwrap(), 0);
+
+        for i in 0..1000_u32 {
+            db.put_cf(&cf, i.to_be_bytes(), b"value").unwrap();
+        }
+        for i in 0..100_u32 {
+            db.delete_cf(&cf, i.to_be_bytes()).unwrap();
+        }
+        assert_eq!(db.count_keys_cf(&cf, true).unwrap(), 900);
+
+        // The estimate becomes exact once the deletes have been compacted away.
+        db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+        assert_eq!(db.count_keys_cf(&cf, true).unwrap(), 900);
+        assert_eq!(db.count_keys_cf(&cf, false).unwrap(), 900);
+    }
+}
+
+#[test]
+fn test_min_log_number_to_keep() {
+    let path = DBPath::new("_rust_rocksdb_test_min_log_number_to_keep");
+    {
+        let db = DB::open_default(&path).unwrap();
+        db.put(b"key1", b"value1").unwrap();
+        let before = db.min_log_number_to_keep().unwrap();
+
+        // Flushing switches to a new WAL; the old one is no longer needed.
+        db.flush().unwrap();
+        let after = db.min_log_number_to_keep().unwrap();
+        assert!(after > before, "{} <= {}", after, before);
+
+        // Unflushed writes keep the current WAL alive.
+        db.put(b"key2", b"value2").unwrap();
+        assert_eq!(db.min_log_number_to_keep().unwrap(), after);
+    }
+}
+
 struct OperationCounts {
     puts: usize,
     deletes: usize,
@@ -509,6 +611,28 @@ fn test_open_as_secondary() {
     assert_eq!(get_byte_slice(&result), b"value2");
 }
 
+#[test]
+fn test_try_catch_up_with_primary_sequence() {
+    let primary_path = DBPath::new("_rust_rocksdb_test_catch_up_sequence_primary");
+    let db = DB::open_default(&primary_path).unwrap();
+    db.put(b"key1", b"value1").unwrap();
+
+    let mut opts = Options::default();
+    opts.set_max_open_files(-1);
+    let secondary_path = DBPath::new("_rust_rocksdb_test_catch_up_sequence_secondary");
+    let secondary = DB::open_as_secondary(&opts, &primary_path, &secondary_path).unwrap();
+    assert_eq!(secondary.latest_sequence_number(), 1);
+
+    db.put(b"key2", b"value2").unwrap();
+    db.put(b"key3", b"value3").unwrap();
+    let primary_seq = db.latest_sequence_number();
+    assert_eq!(
+        secondary.try_catch_up_with_primary_sequence().unwrap(),
+        primary_seq
+    );
+    assert_eq!(secondary.get(b"key3").unwrap().unwrap(), b"value3");
+}
+
 #[test]
 fn test_open_cf_descriptors_as_secondary() {
     let primary_path = DBPath::new("_rust_rocksdb_test_open_cf_descriptors_as_secondary_primary");
@@ -584,6 +708,113 @@ fn test_open_cf_with_ttl() {
     assert!(db.get_cf(&cf, b"key1").unwrap().is_none());
 }
 
+#[test]
+fn test_open_builder_read_only_descriptors() {
+    let path = DBPath::new("_rust_rocksdb_test_open_builder_read_only_descriptors");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put_cf(&cf1, b"k1", b"v1").unwrap();
+    }
+
+    let cfs = vec![ColumnFamilyDescriptor::new("cf1", Options::default())];
+    let db: DB = OpenBuilder::new(Options::default())
+        .read_only(true)
+        .descriptors(cfs)
+        .open(&path)
+        .unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+    assert!(db.put_cf(&cf1, b"k2", b"v2").is_err());
+}
+
+#[test]
+fn test_open_builder_secondary_descriptors() {
+    let primary_path = DBPath::new("_rust_rocksdb_test_open_builder_secondary_primary");
+    let mut primary_opts = Options::default();
+    primary_opts.create_if_missing(true);
+    primary_opts.create_missing_column_families(true);
+    let primary_db = DB::open_cf(&primary_opts, &primary_path, &["cf1"]).unwrap();
+    let primary_cf1 = primary_db.cf_handle("cf1").unwrap();
+    primary_db.put_cf(&primary_cf1, b"k1", b"v1").unwrap();
+
+    let secondary_path = DBPath::new("_rust_rocksdb_test_open_builder_secondary_secondary");
+    let mut secondary_opts = Options::default();
+    secondary_opts.set_max_open_files(-1);
+    let cfs = vec![ColumnFamilyDescriptor::new("cf1", Options::default())];
+    let secondary_db: DB = OpenBuilder::new(secondary_opts)
+        .secondary(&secondary_path)
+        .descriptors(cfs)
+        .open(&primary_path)
+        .unwrap();
+    let secondary_cf1 = secondary_db.cf_handle("cf1").unwrap();
+    assert_eq!(
+        secondary_db.get_cf(&secondary_cf1, b"k1").unwrap().unwrap(),
+        b"v1"
+    );
+    assert!(secondary_db.put_cf(&secondary_cf1, b"k2", b"v2").is_err());
+
+    primary_db.put_cf(&primary_cf1, b"k1", b"v2").unwrap();
+    assert!(secondary_db.try_catch_up_with_primary().is_ok());
+    assert_eq!(
+        secondary_db.get_cf(&secondary_cf1, b"k1").unwrap().unwrap(),
+        b"v2"
+    );
+}
+
+#[test]
+fn test_open_builder_ttl_per_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_open_builder_ttl_per_cf");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let cfs = vec![
+        ColumnFamilyDescriptor::new("short", Options::default()),
+        ColumnFamilyDescriptor::new("long", Options::default()),
+    ];
+    let db: DB = OpenBuilder::new(opts)
+        .ttl_per_cf(vec![
+            ("short", Duration::from_secs(1)),
+            ("long", Duration::from_secs(3600)),
+        ])
+        .descriptors(cfs)
+        .open(&path)
+        .unwrap();
+    let short = db.cf_handle("short").unwrap();
+    let long = db.cf_handle("long").unwrap();
+    db.put_cf(&short, b"key1", b"value1").unwrap();
+    db.put_cf(&long, b"key1", b"value1").unwrap();
+
+    thread::sleep(Duration::from_secs(2));
+    db.compact_range_cf(&short, None::<&[u8]>, None::<&[u8]>);
+    db.compact_range_cf(&long, None::<&[u8]>, None::<&[u8]>);
+
+    assert!(db.get_cf(&short, b"key1").unwrap().is_none());
+    assert!(db.get_cf(&long, b"key1").unwrap().is_some());
+}
+
+#[test]
+fn test_open_builder_conflicting_modes() {
+    let path = DBPath::new("_rust_rocksdb_test_open_builder_conflicting_modes");
+    let secondary_path = DBPath::new("_rust_rocksdb_test_open_builder_conflicting_modes_2nd");
+
+    let result: Result<DB, _> = OpenBuilder::new(Options::default())
+        .read_only(true)
+        .secondary(&secondary_path)
+        .open(&path);
+    assert!(result.is_err());
+
+    let result: Result<DB, _> = OpenBuilder::new(Options::default())
+        .read_only(true)
+        .ttl_per_cf(vec![("default", Duration::from_secs(1))])
+        .open(&path);
+    assert!(result.is_err());
+}
+
 #[test]
 fn test_open_as_single_threaded() {
     let primary_path = DBPath::new("_rust_rocksdb_test_open_as_single_threaded");
@@ -678,6 +909,51 @@ fn compact_range_test() {
     }
 }
 
+#[test]
+fn compact_range_cf_opt_change_level() {
+    let path = DBPath::new("_rust_rocksdb_compact_range_cf_opt_change_level");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let key = |i: usize| format!("k{:03}", i).into_bytes();
+
+    for i in 0..100 {
+        db.put_cf(&cf1, key(i), b"v").unwrap();
+    }
+    db.flush_cf(&cf1).unwrap();
+    for i in 0..50 {
+        db.delete_cf(&cf1, key(i)).unwrap();
+    }
+    db.flush_cf(&cf1).unwrap();
+
+    // A full rewrite drops the tombstones, and the result is moved back to level 0.
+    let mut compact_opts = CompactOptions::default();
+    compact_opts.set_exclusive_manual_compaction(true);
+    compact_opts.set_bottommost_level_compaction(BottommostLevelCompaction::Force);
+    compact_opts.set_change_level(true);
+    compact_opts.set_target_level(0);
+    db.compact_range_cf_opt(&cf1, None::<&[u8]>, None::<&[u8]>, &compact_opts);
+
+    let files: Vec<_> = db
+        .live_files()
+        .unwrap()
+        .into_iter()
+        .filter(|f| f.column_family_name == "cf1")
+        .collect();
+    assert!(!files.is_empty());
+    for f in &files {
+        assert_eq!(f.level, 0);
+        assert_eq!(f.num_deletions, 0);
+    }
+    assert_eq!(files.iter().map(|f| f.num_entries).sum::<u64>(), 50);
+    for i in 0..100 {
+        assert_eq!(db.get_cf(&cf1, key(i)).unwrap().is_some(), i >= 50, "{}", i);
+    }
+}
+
 #[test]
 fn fifo_compaction_test() {
     let path = DBPath::new("_rust_rocksdb_fifo_compaction_test");
@@ -791,6 +1067,42 @@ fn prefix_extract_and_iterate_test() {
     }
 }
 
+#[test]
+fn total_order_scan_with_prefix_bloom() {
+    let path = DBPath::new("_rust_rocksdb_total_order_scan_with_prefix_bloom");
+    {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_bloom_filter(10.0, false);
+        block_opts.set_whole_key_filtering(false);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_prefix_extractor(SliceTransform::create_fixed_prefix(2));
+        opts.set_memtable_prefix_bloom_ratio(0.1);
+        opts.set_block_based_table_factory(&block_opts);
+
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(b"p1_k1", b"v1").unwrap();
+        db.put(b"p2_k2", b"v2").unwrap();
+        db.flush().unwrap();
+        db.put(b"p1_k3", b"v3").unwrap();
+        db.put(b"p3_k4", b"v4").unwrap();
+
+        let mut readopts = ReadOptions::default();
+        readopts.set_total_order_seek(true);
+        readopts.set_prefix_same_as_start(false);
+
+        assert_iter(
+            db.iterator_opt(IteratorMode::From(b"p1", Direction::Forward), readopts),
+            &[
+                pair(b"p1_k1", b"v1"),
+                pair(b"p1_k3", b"v3"),
+                pair(b"p2_k2", b"v2"),
+                pair(b"p3_k4", b"v4"),
+            ],
+        );
+    }
+}
+
 #[test]
 fn get_with_cache_and_bulkload_test() {
     let path = DBPath::new("_rust_rocksdb_get_with_cache_and_bulkload_test");
@@ -1123,6 +1435,41 @@ fn test_open_cf_descriptors_for_read_only() {
     }
 }
 
+#[test]
+fn test_open_cf_descriptors_for_read_only_subset() {
+    let path = DBPath::new("_rust_rocksdb_test_open_cf_descriptors_for_read_only_subset");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1", "cf2", "cf3"]).unwrap();
+        for name in &["cf1", "cf2", "cf3"] {
+            let cf = db.cf_handle(name).unwrap();
+            db.put_cf(&cf, b"k1", name.as_bytes()).unwrap();
+        }
+    }
+    {
+        // The WAL of the previous session was never flushed.
+        let cfs = ["cf1", "cf2"]
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        assert!(DB::open_cf_descriptors_read_only(&Options::default(), &path, cfs, true).is_err());
+    }
+    {
+        let cfs = ["cf1", "cf2"]
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(*name, Options::default()));
+        let db = DB::open_cf_descriptors_read_only(&Options::default(), &path, cfs, false).unwrap();
+        assert!(db.cf_handle("cf3").is_none());
+        for name in &["cf1", "cf2"] {
+            let cf = db.cf_handle(name).unwrap();
+            assert_eq!(db.get_cf(&cf, b"k1").unwrap().unwrap(), name.as_bytes());
+            assert!(db.put_cf(&cf, b"k2", b"v2").is_err());
+            assert!(db.delete_cf(&cf, b"k1").is_err());
+        }
+    }
+}
+
 #[test]
 fn delete_range_test() {
     let path = DBPath::new("_rust_rocksdb_delete_range_test");
@@ -1150,6 +1497,170 @@ fn delete_range_test() {
     }
 }
 
+#[test]
+fn delete_range_keeps_keys_outside_range() {
+    let path = DBPath::new("_rust_rocksdb_delete_range_keeps_keys_outside_range");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        let key = |i: usize| format!("k{:03}", i).into_bytes();
+        for i in 0..100 {
+            db.put(key(i), b"v").unwrap();
+            db.put_cf(&cf1, key(i), b"v").unwrap();
+        }
+
+        // Both ranges are half-open: the end key is kept.
+        let mut batch = WriteBatch::default();
+        batch.delete_range(key(20), key(40));
+        db.write(batch).unwrap();
+        db.delete_range_cf(&cf1, key(50), key(80)).unwrap();
+
+        let keys: Vec<_> = db
+            .iterator(IteratorMode::Start)
+            .map(|kv| kv.unwrap().0.to_vec())
+            .collect();
+        assert_eq!(keys, (0..20).chain(40..100).map(key).collect::<Vec<_>>());
+        let keys: Vec<_> = db
+            .iterator_cf(&cf1, IteratorMode::Start)
+            .map(|kv| kv.unwrap().0.to_vec())
+            .collect();
+        assert_eq!(keys, (0..50).chain(80..100).map(key).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn delete_range_validation_test() {
+    let path = DBPath::new("_rust_rocksdb_delete_range_validation_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put_cf(&cf1, b"k1", b"v1").unwrap();
+        db.put_cf(&cf1, b"k2", b"v2").unwrap();
+
+        let err = db.delete_range_cf(&cf1, b"k2", b"k1").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        // The rejected range must not have reached the WAL and poisoned later writes.
+        db.put_cf(&cf1, b"k3", b"v3").unwrap();
+        assert_eq!(db.get_cf(&cf1, b"k3").unwrap().unwrap(), b"v3");
+
+        db.delete_range_cf(&cf1, b"k1", b"k1").unwrap();
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get_cf(&cf1, b"k2").unwrap().unwrap(), b"v2");
+    }
+}
+
+#[test]
+fn delete_range_validation_batch_test() {
+    let path = DBPath::new("_rust_rocksdb_delete_range_validation_batch_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(&cf1, b"k1", b"v1");
+        batch.delete_range_cf(&cf1, b"k2", b"k1");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(db.get_cf(&cf1, b"k1").unwrap().is_none());
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"k1", b"v1");
+        batch.delete_range(b"k2", b"k1");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(db.get(b"k1").unwrap().is_none());
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"k1", b"v1");
+        batch.put_cf(&cf1, b"k1", b"v1");
+        batch.delete_range(b"k1", b"k1");
+        db.write(batch).unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+    }
+}
+
+#[test]
+fn delete_range_validation_reverse_comparator_test() {
+    let path = DBPath::new("_rust_rocksdb_delete_range_validation_reverse_comparator_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut cf_opts = Options::default();
+        cf_opts.set_comparator("reverse", |a, b| b.cmp(a));
+        let db = DB::open_cf_descriptors(
+            &opts,
+            &path,
+            vec![ColumnFamilyDescriptor::new("cf1", cf_opts)],
+        )
+        .unwrap();
+
+        let cf1 = db.cf_handle("cf1").unwrap();
+        for key in [b"k1", b"k2", b"k3"] {
+            db.put_cf(&cf1, key, b"v").unwrap();
+        }
+
+        // In reverse order, "k1" comes after "k3".
+        let err = db.delete_range_cf(&cf1, b"k1", b"k3").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        db.put_cf(&cf1, b"k4", b"v").unwrap();
+        assert_eq!(db.get_cf(&cf1, b"k4").unwrap().unwrap(), b"v");
+
+        db.delete_range_cf(&cf1, b"k3", b"k1").unwrap();
+        assert!(db.get_cf(&cf1, b"k3").unwrap().is_none());
+        assert!(db.get_cf(&cf1, b"k2").unwrap().is_none());
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v");
+
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(&cf1, b"k1", b"k4");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        let mut batch = WriteBatch::default();
+        batch.delete_range_cf(&cf1, b"k4", b"k1");
+        db.write(batch).unwrap();
+        assert!(db.get_cf(&cf1, b"k4").unwrap().is_none());
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v");
+    }
+}
+
+#[test]
+fn delete_range_validation_reverse_default_comparator_test() {
+    let path = DBPath::new("_rust_rocksdb_delete_range_validation_reverse_default_comparator_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_comparator("reverse", |a, b| b.cmp(a));
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(b"k1", b"v").unwrap();
+        db.put(b"k2", b"v").unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.delete_range(b"k1", b"k2");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        db.put(b"k3", b"v").unwrap();
+
+        let mut batch = WriteBatch::default();
+        batch.delete_range(b"k3", b"k1");
+        db.write(batch).unwrap();
+        assert!(db.get(b"k3").unwrap().is_none());
+        assert!(db.get(b"k2").unwrap().is_none());
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v");
+    }
+}
+
 #[test]
 fn multi_get() {
     let path = DBPath::new("_rust_rocksdb_multi_get");
@@ -1212,6 +1723,39 @@ fn multi_get() {
     }
 }
 
+#[test]
+fn multi_get_ok_or_first_err() {
+    let path = DBPath::new("_rust_rocksdb_multi_get_ok_or_first_err");
+
+    {
+        let db = DB::open_default(&path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.flush().unwrap();
+        db.put(b"k2", b"v2").unwrap();
+
+        let values = db
+            .multi_get_ok_or_first_err(&[b"k0", b"k1", b"k2"])
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![None, Some(b"v1".to_vec()), Some(b"v2".to_vec())]
+        );
+
+        // Restricting reads to the block cache makes the lookup of the flushed key fail with
+        // `Incomplete`, while the key still in the memtable is found.
+        let mut readopts = ReadOptions::default();
+        readopts.set_read_tier(ReadTier::BlockCache);
+        let results = db.multi_get_opt(&[b"k2", b"k1"], &readopts);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+
+        let err = db
+            .multi_get_ok_or_first_err_opt(&[b"k2", b"k1"], &readopts)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Incomplete);
+    }
+}
+
 #[test]
 fn multi_get_cf() {
     let path = DBPath::new("_rust_rocksdb_multi_get_cf");
@@ -1242,6 +1786,41 @@ fn multi_get_cf() {
     }
 }
 
+#[test]
+fn multi_get_cf_interleaved() {
+    let path = DBPath::new("_rust_rocksdb_multi_get_cf_interleaved");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, &["cf1", "cf2"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let cf2 = db.cf_handle("cf2").unwrap();
+    db.put_cf(&cf1, b"k1", b"cf1_v1").unwrap();
+    db.put_cf(&cf2, b"k1", b"cf2_v1").unwrap();
+    db.put_cf(&cf2, b"k2", b"cf2_v2").unwrap();
+
+    // The same key is read from the column family it's paired with.
+    let values = db
+        .multi_get_cf(vec![
+            (&cf2, b"k2"),
+            (&cf1, b"k1"),
+            (&cf1, b"k2"),
+            (&cf2, b"k1"),
+        ])
+        .into_iter()
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        vec![
+            Some(b"cf2_v2".to_vec()),
+            Some(b"cf1_v1".to_vec()),
+            None,
+            Some(b"cf2_v1".to_vec()),
+        ]
+    );
+}
+
 #[test]
 fn batched_multi_get_cf() {
     let path = DBPath::new("_rust_rocksdb_batched_multi_get_cf");
@@ -1269,6 +1848,143 @@ fn batched_multi_get_cf() {
     }
 }
 
+#[test]
+fn batched_multi_get_cf_matches_get_pinned_cf() {
+    let path = DBPath::new("_rust_rocksdb_batched_multi_get_cf_matches_get_pinned_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, &["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    let key = |i: usize| format!("k{:03}", i).into_bytes();
+
+    // Some values are read from an SST file, others from the memtable.
+    for i in (0..100).step_by(2) {
+        db.put_cf(&cf, key(i), vec![i as u8; 1_000]).unwrap();
+    }
+    db.flush_cf(&cf).unwrap();
+    for i in (1..100).step_by(4) {
+        db.put_cf(&cf, key(i), vec![i as u8; 1_000]).unwrap();
+    }
+
+    let sorted_keys: Vec<_> = (0..100).map(key).collect();
+    let unsorted_keys: Vec<_> = sorted_keys.iter().rev().cloned().collect();
+    for (keys, sorted_input) in [(&sorted_keys, true), (&unsorted_keys, false)] {
+        let values = db.batched_multi_get_cf(&cf, keys, sorted_input);
+        assert_eq!(values.len(), keys.len());
+        for (key, value) in keys.iter().zip(values) {
+            let expected = db.get_pinned_cf(&cf, key).unwrap();
+            assert_eq!(value.unwrap().as_deref(), expected.as_deref(), "{:?}", key);
+        }
+    }
+}
+
+#[test]
+fn batched_multi_get_cf_sorted_by_comparator() {
+    let path = DBPath::new("_rust_rocksdb_batched_multi_get_cf_sorted_by_comparator");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let mut cf_opts = Options::default();
+    cf_opts.set_comparator("reverse", |a, b| b.cmp(a));
+    let db = DB::open_cf_descriptors(
+        &opts,
+        &path,
+        vec![ColumnFamilyDescriptor::new("cf", cf_opts)],
+    )
+    .unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    for i in 0..10_u8 {
+        db.put_cf(&cf, [i], [i]).unwrap();
+    }
+    db.flush_cf(&cf).unwrap();
+
+    // "Sorted" means sorted by the column family comparator, not bytewise.
+    let keys: Vec<_> = (0..12_u8).rev().map(|i| [i]).collect();
+    let values = db.batched_multi_get_cf(&cf, &keys, true);
+    for (key, value) in keys.iter().zip(values) {
+        let expected = if key[0] < 10 { Some(&key[..]) } else { None };
+        assert_eq!(value.unwrap().as_deref(), expected, "{:?}", key);
+    }
+}
+
+#[test]
+fn multi_get_prefix_cf() {
+    let path = DBPath::new("_rust_rocksdb_multi_get_prefix_cf");
+
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut cf_opts = Options::default();
+        cf_opts.set_prefix_extractor(SliceTransform::create_fixed_prefix(4));
+        let cfs = vec![ColumnFamilyDescriptor::new("cf0", cf_opts)];
+        let db = DB::open_cf_descriptors(&opts, &path, cfs).unwrap();
+
+        let cf = db.cf_handle("cf0").unwrap();
+        db.put_cf(&cf, b"user1", b"v1").unwrap();
+        db.put_cf(&cf, b"user3", b"v3").unwrap();
+        db.put_cf(&cf, b"item1", b"other").unwrap();
+        db.flush_cf(&cf).unwrap();
+        db.put_cf(&cf, b"user2", b"v2").unwrap();
+
+        let suffixes: [&[u8]; 5] = [b"3", b"0", b"1", b"2", b"4"];
+        let values = db
+            .multi_get_prefix_cf(&cf, b"user", suffixes)
+            .into_iter()
+            .map(|v| v.unwrap().map(|v| v.to_vec()))
+            .collect::<Vec<_>>();
+        let expected = suffixes
+            .iter()
+            .map(|suffix| db.get_cf(&cf, [&b"user"[..], suffix].concat()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+        assert_eq!(
+            values,
+            vec![
+                Some(b"v3".to_vec()),
+                None,
+                Some(b"v1".to_vec()),
+                Some(b"v2".to_vec()),
+                None
+            ]
+        );
+    }
+}
+
+#[test]
+fn multi_get_prefix_cf_reverse_comparator() {
+    let path = DBPath::new("_rust_rocksdb_multi_get_prefix_cf_reverse_comparator");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let mut cf_opts = Options::default();
+    cf_opts.set_comparator("reverse", |a, b| b.cmp(a));
+    let db = DB::open_cf_descriptors(
+        &opts,
+        &path,
+        vec![ColumnFamilyDescriptor::new("cf", cf_opts)],
+    )
+    .unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    for i in 0..10_u8 {
+        db.put_cf(&cf, [b'k', i], [i]).unwrap();
+    }
+    db.flush_cf(&cf).unwrap();
+
+    // Bytewise ascending, so out of order for the column family comparator.
+    let suffixes: Vec<_> = (0..12_u8).map(|i| [i]).collect();
+    let values = db.multi_get_prefix_cf(&cf, b"k", &suffixes);
+    for (suffix, value) in suffixes.iter().zip(values) {
+        let expected = if suffix[0] < 10 {
+            Some(&suffix[..])
+        } else {
+            None
+        };
+        assert_eq!(value.unwrap().as_deref(), expected, "{:?}", suffix);
+    }
+}
+
 #[test]
 fn key_may_exist() {
     let path = DBPath::new("_rust_key_may_exist");
@@ -1296,12 +2012,83 @@ fn key_may_exist_cf() {
     }
 }
 
+#[test]
+fn key_may_exist_value() {
+    let path = DBPath::new("_rust_key_may_exist_value");
+
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf"]).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+        let readopts = ReadOptions::default();
+        db.put(b"k1", b"v1").unwrap();
+        db.put_cf(&cf, b"k2", b"v2").unwrap();
+
+        assert!(db.key_may_exist(b"k1"));
+        assert!(db.key_may_exist_cf(&cf, b"k2"));
+
+        // Values still in the memtable are found without I/O.
+        assert_eq!(
+            db.key_may_exist_opt_value(b"k1", &readopts),
+            (true, Some(b"v1".to_vec()))
+        );
+        assert_eq!(
+            db.key_may_exist_cf_opt_value(&cf, b"k2", &readopts),
+            (true, Some(b"v2".to_vec()))
+        );
+        assert_eq!(
+            db.key_may_exist_opt_value(b"nonexistent", &readopts),
+            (false, None)
+        );
+        assert_eq!(
+            db.key_may_exist_cf_opt_value(&cf, b"k1", &readopts),
+            (false, None)
+        );
+    }
+}
+
 #[test]
 fn test_snapshot_outlive_db() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/fail/snapshot_outlive_db.rs");
 }
 
+#[test]
+fn test_snapshot_read_options() {
+    let path = DBPath::new("_rust_rocksdb_test_snapshot_read_options");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put_cf(&cf1, b"k1", b"v1").unwrap();
+
+        let snapshot = db.snapshot();
+        let readopts = ReadOptions::default().with_snapshot(&snapshot);
+        db.put(b"k1", b"v2").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+        db.put_cf(&cf1, b"k1", b"v2").unwrap();
+
+        assert_eq!(db.get_opt(b"k1", &readopts).unwrap().unwrap(), b"v1");
+        assert!(db.get_opt(b"k2", &readopts).unwrap().is_none());
+        assert_eq!(
+            db.get_cf_opt(&cf1, b"k1", &readopts).unwrap().unwrap(),
+            b"v1"
+        );
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v2");
+    }
+}
+
+#[test]
+fn test_snapshot_read_options_outlive_snapshot() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/fail/snapshot_read_options_outlive_snapshot.rs");
+}
+
 #[test]
 fn cuckoo() {
     let path = DBPath::new("_rust_rocksdb_cuckoo");
@@ -1331,3 +2118,323 @@ fn cuckoo() {
         assert!(db.get(b"k1").unwrap().is_none());
     }
 }
+
+#[test]
+fn test_close() {
+    let path = DBPath::new("_rust_rocksdb_test_close");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    {
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        // Without the WAL, the writes only survive if close flushes the memtables.
+        let mut writeopts = WriteOptions::default();
+        writeopts.disable_wal(true);
+        db.put_opt(b"k1", b"v1", &writeopts).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put_cf_opt(&cf1, b"k2", b"v2", &writeopts).unwrap();
+        db.close(true).unwrap();
+    }
+    {
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        let cf1 = db.cf_handle("cf1").unwrap();
+        assert_eq!(db.get_cf(&cf1, b"k2").unwrap().unwrap(), b"v2");
+        // Without flushing first, close still persists the unflushed writes on shutdown.
+        let mut writeopts = WriteOptions::default();
+        writeopts.disable_wal(true);
+        db.put_opt(b"k3", b"v3", &writeopts).unwrap();
+        db.close(false).unwrap();
+    }
+    {
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get(b"k3").unwrap().unwrap(), b"v3");
+    }
+}
+
+#[test]
+fn test_close_background_error() {
+    fn failing_merge(
+        _key: &[u8],
+        _val: Option<&[u8]>,
+        _operands: &MergeOperands,
+    ) -> Option<Vec<u8>> {
+        None
+    }
+
+    let path = DBPath::new("_rust_rocksdb_test_close_background_error");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_merge_operator_associative("fail", failing_merge);
+    let db = DB::open(&opts, &path).unwrap();
+    // Writes without the WAL are flushed when background work is cancelled on close, and the
+    // merge fails during that flush.
+    let mut writeopts = WriteOptions::default();
+    writeopts.disable_wal(true);
+    db.put_opt(b"k1", b"v1", &writeopts).unwrap();
+    db.merge_opt(b"k1", b"v2", &writeopts).unwrap();
+    assert_eq!(
+        db.property_int_value(properties::BACKGROUND_ERRORS)
+            .unwrap(),
+        Some(0)
+    );
+    assert!(db.close(false).is_err());
+}
+
+#[test]
+fn skip_checks_on_db_open() {
+    let path = DBPath::new("_rust_rocksdb_skip_checks_on_db_open");
+    {
+        let db = DB::open_default(&path).unwrap();
+        // Leave a few SST files behind for the open to skip over.
+        for i in 0..3 {
+            db.put(format!("k{}", i), format!("v{}", i)).unwrap();
+            db.flush().unwrap();
+        }
+    }
+    {
+        let mut opts = Options::default();
+        opts.set_skip_stats_update_on_db_open(true);
+        opts.set_skip_checking_sst_file_sizes_on_db_open(true);
+        let db = DB::open(&opts, &path).unwrap();
+        for i in 0..3 {
+            assert_eq!(
+                db.get(format!("k{}", i)).unwrap().unwrap(),
+                format!("v{}", i).as_bytes()
+            );
+        }
+    }
+}
+
+#[test]
+fn path_of_opened_db() {
+    let path = DBPath::new("_rust_rocksdb_path_of_opened_db");
+    let db = DB::open_default(&path).unwrap();
+    let expected: &std::path::Path = (&path).as_ref();
+    assert_eq!(db.path(), expected);
+}
+
+#[test]
+fn delete_files_in_ranges() {
+    let path = DBPath::new("_rust_rocksdb_delete_files_in_ranges");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, &["cf1", "cf2"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let cf2 = db.cf_handle("cf2").unwrap();
+    let key = |i: usize| format!("k{:03}", i).into_bytes();
+
+    // Each column family gets two non-overlapping SST files below L0.
+    for cf in [&cf1, &cf2] {
+        for chunk in &[0..50, 50..100] {
+            for i in chunk.clone() {
+                db.put_cf(cf, key(i), b"value").unwrap();
+            }
+            db.flush_cf(cf).unwrap();
+            db.compact_range_cf(cf, Some(key(chunk.start)), Some(key(chunk.end - 1)));
+        }
+    }
+
+    db.delete_files_in_ranges(vec![(&cf1, key(0), key(49)), (&cf2, key(50), key(99))])
+        .unwrap();
+
+    for i in 0..100 {
+        assert_eq!(db.get_cf(&cf1, key(i)).unwrap().is_some(), i >= 50, "{}", i);
+        assert_eq!(db.get_cf(&cf2, key(i)).unwrap().is_some(), i < 50, "{}", i);
+    }
+}
+
+#[test]
+fn warm_cache_cf() {
+    let path = DBPath::new("_rust_rocksdb_warm_cache_cf");
+    let cache = Cache::new_lru_cache(8 << 20).unwrap();
+    let mut block_opts = BlockBasedOptions::default();
+    block_opts.set_block_cache(&cache);
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_block_based_table_factory(&block_opts);
+    let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let key = |i: usize| format!("k{:04}", i).into_bytes();
+
+    for i in 0..1_000 {
+        db.put_cf(&cf1, key(i), [b'v'; 100]).unwrap();
+    }
+    db.flush_cf(&cf1).unwrap();
+
+    let usage = cache.get_usage();
+    db.warm_cache_cf(&cf1, key(0), key(500)).unwrap();
+    let warmed_usage = cache.get_usage();
+    assert!(warmed_usage > usage);
+
+    // Warming the rest of the keys loads more blocks.
+    db.warm_cache_cf(&cf1, key(500), key(1_000)).unwrap();
+    assert!(cache.get_usage() > warmed_usage);
+}
+
+#[test]
+fn scan_without_filling_cache() {
+    let path = DBPath::new("_rust_rocksdb_scan_without_filling_cache");
+    let cache = Cache::new_lru_cache(8 << 20).unwrap();
+    let mut block_opts = BlockBasedOptions::default();
+    block_opts.set_block_cache(&cache);
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_block_based_table_factory(&block_opts);
+    let db = DB::open(&opts, &path).unwrap();
+
+    for i in 0..1_000 {
+        db.put(format!("k{:04}", i), [b'v'; 100]).unwrap();
+    }
+    db.flush().unwrap();
+
+    let usage = cache.get_usage();
+    let mut readopts = ReadOptions::default();
+    readopts.fill_cache(false);
+    assert_eq!(
+        db.iterator_opt(IteratorMode::Start, readopts).count(),
+        1_000
+    );
+    assert_eq!(cache.get_usage(), usage);
+
+    assert_eq!(db.iterator(IteratorMode::Start).count(), 1_000);
+    assert!(cache.get_usage() > usage);
+}
+
+#[test]
+fn flush_to_level_cf() {
+    let path = DBPath::new("_rust_rocksdb_flush_to_level_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let cf1_levels = || {
+        db.live_files()
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.column_family_name == "cf1")
+            .map(|f| f.level)
+            .collect::<Vec<_>>()
+    };
+
+    db.put_cf(&cf1, b"k1", b"v1").unwrap();
+    db.flush_to_level_cf(&cf1, 3).unwrap();
+    assert_eq!(cf1_levels(), vec![3]);
+
+    // Files already in the column family are moved along with the flushed ones.
+    db.put_cf(&cf1, b"k2", b"v2").unwrap();
+    db.flush_to_level_cf(&cf1, 3).unwrap();
+    let levels = cf1_levels();
+    assert!(!levels.is_empty());
+    assert!(levels.iter().all(|&level| level == 3), "{:?}", levels);
+    assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+    assert_eq!(db.get_cf(&cf1, b"k2").unwrap().unwrap(), b"v2");
+}
+
+#[test]
+fn level_key_stats_cf() {
+    let path = DBPath::new("_rust_rocksdb_level_key_stats_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    let key = |i: usize| format!("k{:03}", i).into_bytes();
+    assert!(db.level_key_stats_cf(&cf1).unwrap().is_empty());
+
+    for i in 0..100 {
+        db.put_cf(&cf1, key(i), b"v").unwrap();
+    }
+    db.flush_to_level_cf(&cf1, 3).unwrap();
+    for i in 100..130 {
+        db.put_cf(&cf1, key(i), b"v").unwrap();
+    }
+    for i in 0..20 {
+        db.delete_cf(&cf1, key(i)).unwrap();
+    }
+    db.flush_cf(&cf1).unwrap();
+    db.put(b"k", b"v").unwrap();
+    db.flush().unwrap();
+
+    let stats = db.level_key_stats_cf(&cf1).unwrap();
+    assert_eq!(
+        stats.iter().map(|s| s.level).collect::<Vec<_>>(),
+        vec![0, 3]
+    );
+    assert_eq!((stats[0].num_entries, stats[0].num_deletions), (50, 20));
+    assert_eq!((stats[1].num_entries, stats[1].num_deletions), (100, 0));
+    assert_eq!(stats.iter().map(|s| s.num_files).sum::<usize>(), 2);
+}
+
+#[test]
+fn live_files_key_range() {
+    let path = DBPath::new("_rust_rocksdb_live_files_key_range");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    assert!(db.live_files().unwrap().is_empty());
+
+    for i in 10..20 {
+        db.put_cf(&cf1, format!("k{}", i), b"v").unwrap();
+    }
+    db.delete_cf(&cf1, b"k15").unwrap();
+    db.flush_cf(&cf1).unwrap();
+
+    let files = db.live_files().unwrap();
+    assert_eq!(files.len(), 1, "{:?}", files);
+    let file = &files[0];
+    assert_eq!(file.column_family_name, "cf1");
+    assert!(file.name.ends_with(".sst"), "{}", file.name);
+    assert!(file.size > 0);
+    assert_eq!(file.level, 0);
+    assert_eq!(file.start_key.as_deref(), Some(&b"k10"[..]));
+    assert_eq!(file.end_key.as_deref(), Some(&b"k19"[..]));
+    assert_eq!((file.num_entries, file.num_deletions), (11, 1));
+}
+
+#[test]
+fn snapshot_released_on_drop() {
+    let path = DBPath::new("_rust_rocksdb_snapshot_released_on_drop");
+    let db = DB::open_default(&path).unwrap();
+    let mut compact_opts = CompactOptions::default();
+    compact_opts.set_bottommost_level_compaction(BottommostLevelCompaction::Force);
+    let compact_and_count = || {
+        db.compact_range_opt(None::<&[u8]>, None::<&[u8]>, &compact_opts);
+        db.live_files()
+            .unwrap()
+            .iter()
+            .map(|f| f.num_entries)
+            .sum::<u64>()
+    };
+
+    db.put(b"k", b"v1").unwrap();
+    {
+        let snapshot = db.snapshot();
+        db.put(b"k", b"v2").unwrap();
+        db.flush().unwrap();
+        assert_eq!(
+            db.property_int_value(properties::NUM_SNAPSHOTS).unwrap(),
+            Some(1)
+        );
+
+        // The version seen by the snapshot survives compaction.
+        assert_eq!(compact_and_count(), 2);
+        assert_eq!(snapshot.get(b"k").unwrap().unwrap(), b"v1");
+    }
+
+    assert_eq!(
+        db.property_int_value(properties::NUM_SNAPSHOTS).unwrap(),
+        Some(0)
+    );
+    assert_eq!(compact_and_count(), 1);
+    assert_eq!(db.get(b"k").unwrap().unwrap(), b"v2");
+}
@@ -378,3 +378,41 @@ fn test_iter_range() {
         check(&db, b"b0", prefix(b"\xff"), 0..0);
     }
 }
+
+#[test]
+fn test_range_iterator_rev_cf() {
+    let path = DBPath::new("_rust_rocksdb_range_iterator_rev_cf_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, &["cf1"]).unwrap();
+        let cf = db.cf_handle("cf1").unwrap();
+
+        for key in [b"a", b"b", b"c", b"d", b"f", b"g"] {
+            db.put_cf(&cf, key, key).unwrap();
+        }
+
+        // `end` exists and is excluded.
+        assert_iter(
+            db.range_iterator_rev_cf(&cf, b"b", b"f"),
+            &[pair(b"d", b"d"), pair(b"c", b"c"), pair(b"b", b"b")],
+        );
+
+        // `end` doesn't exist; iteration starts from the preceding key.
+        assert_iter(
+            db.range_iterator_rev_cf(&cf, b"c", b"e"),
+            &[pair(b"d", b"d"), pair(b"c", b"c")],
+        );
+
+        // `start` doesn't exist either.
+        assert_iter(
+            db.range_iterator_rev_cf(&cf, b"bb", b"ff"),
+            &[pair(b"f", b"f"), pair(b"d", b"d"), pair(b"c", b"c")],
+        );
+
+        // Empty ranges.
+        assert_iter(db.range_iterator_rev_cf(&cf, b"d", b"d"), &[]);
+        assert_iter(db.range_iterator_rev_cf(&cf, b"dd", b"e"), &[]);
+    }
+}
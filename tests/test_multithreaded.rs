@@ -16,7 +16,7 @@ mod util;
 
 use std::{sync::Arc, thread};
 
-use rocksdb::DB;
+use rocksdb::{Options, DB};
 use util::DBPath;
 
 const N: usize = 100_000;
@@ -58,3 +58,37 @@ pub fn test_multithreaded() {
         j3.join().unwrap();
     }
 }
+
+#[test]
+pub fn test_multithreaded_adaptive_yield() {
+    let n = DBPath::new("_rust_rocksdb_multithreadtest_adaptive_yield");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_allow_concurrent_memtable_write(true);
+        opts.set_enable_write_thread_adaptive_yield(true);
+        let db = Arc::new(DB::open(&opts, &n).unwrap());
+
+        let handles: Vec<_> = (0..4u8)
+            .map(|t| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for i in 0..N / 10 {
+                        let key = format!("{}_{}", t, i);
+                        db.put(key.as_bytes(), [t]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        for t in 0..4u8 {
+            for i in 0..N / 10 {
+                let key = format!("{}_{}", t, i);
+                assert_eq!(db.get(key.as_bytes()).unwrap().unwrap(), [t]);
+            }
+        }
+    }
+}
@@ -22,6 +22,8 @@ use rocksdb::{
 };
 use util::DBPath;
 
+use std::{convert::TryInto, sync::Arc, thread};
+
 #[test]
 fn open_default() {
     let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_open_default");
@@ -321,6 +323,48 @@ fn snapshot_test() {
     }
 }
 
+#[test]
+fn snapshot_repeatable_read() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_snapshot_repeatable_read");
+    {
+        let db: OptimisticTransactionDB = OptimisticTransactionDB::open_default(&path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+
+        let snap = db.snapshot();
+
+        db.put(b"k1", b"v1_new").unwrap();
+        db.delete(b"k2").unwrap();
+        db.put(b"k3", b"v3").unwrap();
+
+        assert_eq!(snap.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(
+            snap.multi_get([b"k1", b"k2", b"k3"])
+                .into_iter()
+                .map(Result::unwrap)
+                .collect::<Vec<_>>(),
+            vec![Some(b"v1".to_vec()), Some(b"v2".to_vec()), None]
+        );
+        assert_eq!(
+            snap.iterator(IteratorMode::Start)
+                .map(|item| {
+                    let (k, v) = item.unwrap();
+                    (k.to_vec(), v.to_vec())
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (b"k1".to_vec(), b"v1".to_vec()),
+                (b"k2".to_vec(), b"v2".to_vec())
+            ]
+        );
+
+        // The DB itself sees the latest writes.
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1_new");
+        assert!(db.get(b"k2").unwrap().is_none());
+        assert_eq!(db.get(b"k3").unwrap().unwrap(), b"v3");
+    }
+}
+
 #[test]
 fn prefix_extract_and_iterate_test() {
     let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_prefix_extract_and_iterate");
@@ -581,3 +625,104 @@ fn transaction_snapshot() {
         assert_eq!(snapshot.get(b"k3").unwrap().unwrap(), b"v3");
     }
 }
+
+#[test]
+fn transaction_snapshot_conflict_window() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_snapshot_conflict_window");
+    {
+        let db: OptimisticTransactionDB = OptimisticTransactionDB::open_default(&path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+
+        // Without a snapshot, conflicts are only checked from the first write on, so a
+        // write made by someone else before that doesn't fail the commit.
+        let txn = db.transaction();
+        db.put(b"k1", b"v2").unwrap();
+        txn.put(b"k1", b"v3").unwrap();
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v3");
+
+        // With a snapshot, conflicts are checked from the start of the transaction.
+        let mut opts = OptimisticTransactionOptions::default();
+        opts.set_snapshot(true);
+        let txn = db.transaction_opt(&WriteOptions::default(), &opts);
+        db.put(b"k1", b"v4").unwrap();
+        txn.put(b"k1", b"v5").unwrap();
+        assert_eq!(txn.commit().unwrap_err().kind(), ErrorKind::Busy);
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v4");
+    }
+}
+
+#[test]
+fn compare_and_swap_cf() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_compare_and_swap_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: OptimisticTransactionDB<SingleThreaded> =
+            OptimisticTransactionDB::open_cf(&opts, &path, ["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+
+        assert!(db.compare_and_swap_cf(&cf1, b"k1", None, b"v1").unwrap());
+        assert!(!db.compare_and_swap_cf(&cf1, b"k1", None, b"v2").unwrap());
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+
+        assert!(!db
+            .compare_and_swap_cf(&cf1, b"k1", Some(&b"v0"[..]), b"v2")
+            .unwrap());
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+        assert!(db
+            .compare_and_swap_cf(&cf1, b"k1", Some(&b"v1"[..]), b"v2")
+            .unwrap());
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v2");
+        assert!(db.get(b"k1").unwrap().is_none());
+    }
+}
+
+#[test]
+fn compare_and_swap_cf_contention() {
+    const INCREMENTS: u64 = 500;
+
+    let path =
+        DBPath::new("_rust_rocksdb_optimistic_transaction_db_compare_and_swap_cf_contention");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: Arc<OptimisticTransactionDB<SingleThreaded>> =
+            Arc::new(OptimisticTransactionDB::open_cf(&opts, &path, ["cf1"]).unwrap());
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put_cf(cf1, b"counter", 0_u64.to_be_bytes()).unwrap();
+
+        // Both threads increment the counter, so their commits conflict with each other.
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    let cf1 = db.cf_handle("cf1").unwrap();
+                    let mut done = 0;
+                    while done < INCREMENTS {
+                        let current = db.get_cf(cf1, b"counter").unwrap().unwrap();
+                        let next =
+                            (u64::from_be_bytes(current[..].try_into().unwrap()) + 1).to_be_bytes();
+                        if db
+                            .compare_and_swap_cf(cf1, b"counter", Some(&current[..]), &next)
+                            .unwrap()
+                        {
+                            done += 1;
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let counter = db.get_cf(cf1, b"counter").unwrap().unwrap();
+        assert_eq!(
+            u64::from_be_bytes(counter[..].try_into().unwrap()),
+            2 * INCREMENTS
+        );
+    }
+}
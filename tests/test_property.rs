@@ -95,3 +95,39 @@ fn property_int_cf_test() {
         assert_eq!(total_keys, Some(0));
     }
 }
+
+#[test]
+fn property_int_cf_after_writes_test() {
+    let n = DBPath::new("_rust_rocksdb_property_int_cf_after_writes_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &n, &["cf1"]).unwrap();
+        let cf = db.cf_handle("cf1").unwrap();
+        for i in 0..100 {
+            db.put_cf(&cf, format!("key{}", i), b"value").unwrap();
+        }
+
+        let cf_keys = db
+            .property_int_value_cf(&cf, properties::ESTIMATE_NUM_KEYS)
+            .unwrap();
+        assert_eq!(cf_keys, Some(100));
+        let default_keys = db
+            .property_int_value(properties::ESTIMATE_NUM_KEYS)
+            .unwrap();
+        assert_eq!(default_keys, Some(0));
+
+        // Undefined properties are reported as missing rather than as an error.
+        assert_eq!(
+            db.property_int_value_cf(&cf, "rocksdb.no-such-property")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            db.property_value_cf(&cf, "rocksdb.no-such-property")
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -16,7 +16,10 @@ mod util;
 
 use std::{fs, io::Read as _};
 
-use rocksdb::{BlockBasedOptions, DBCompressionType, DataBlockIndexType, Options, ReadOptions, DB};
+use rocksdb::{
+    properties, BlockBasedOptions, CompactionDecision, DBCompressionType, DataBlockIndexType,
+    ErrorKind, IteratorMode, Options, ReadOptions, WriteOptions, DB,
+};
 use util::DBPath;
 
 #[test]
@@ -202,3 +205,415 @@ fn test_lz4_compression() {
 fn test_zstd_compression() {
     test_compression_type(DBCompressionType::Zstd);
 }
+
+#[test]
+fn test_set_max_total_wal_size() {
+    let path = DBPath::new("_rust_rocksdb_test_set_max_total_wal_size");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        // Large enough memtables so that only the WAL limit can cause a flush.
+        opts.set_write_buffer_size(64 << 20);
+        opts.set_max_total_wal_size(64 << 10);
+        let db = DB::open_cf(&opts, &path, &["cf1", "cf2"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        let cf2 = db.cf_handle("cf2").unwrap();
+
+        let value = vec![b'x'; 1024];
+        for i in 0..1024_u32 {
+            let key = i.to_be_bytes();
+            db.put_cf(&cf1, key, &value).unwrap();
+            db.put_cf(&cf2, key, &value).unwrap();
+        }
+
+        // Exceeding the WAL limit switches out the memtables backed by the oldest WAL.
+        for cf in [cf1, cf2] {
+            let active = db
+                .property_int_value_cf(&cf, properties::NUM_ENTRIES_ACTIVE_MEM_TABLE)
+                .unwrap()
+                .unwrap();
+            assert!(active < 1024, "{} entries in active memtable", active);
+        }
+
+        let mut retries = 0;
+        while db.live_files().unwrap().is_empty() {
+            retries += 1;
+            assert!(retries < 100, "no flush was triggered");
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+    }
+}
+
+#[test]
+fn test_set_inplace_update_support() {
+    let path = DBPath::new("_rust_rocksdb_test_set_inplace_update_support");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_inplace_update_support(true);
+        opts.set_inplace_update_locks(16);
+        assert!(DB::open(&opts, &path).is_err());
+
+        opts.set_allow_concurrent_memtable_write(false);
+        let db = DB::open(&opts, &path).unwrap();
+
+        let keys = [b"k1", b"k2", b"k3"];
+        for round in 0..100_u32 {
+            for key in &keys {
+                db.put(key, round.to_be_bytes()).unwrap();
+            }
+        }
+        for key in &keys {
+            assert_eq!(db.get(key).unwrap().unwrap(), 99_u32.to_be_bytes());
+        }
+    }
+}
+
+#[test]
+fn test_set_bytes_per_sync() {
+    let path = DBPath::new("_rust_rocksdb_test_set_bytes_per_sync");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_bytes_per_sync(64 << 10);
+        opts.set_wal_bytes_per_sync(64 << 10);
+        opts.set_write_buffer_size(256 << 10);
+        let db = DB::open(&opts, &path).unwrap();
+
+        let value = vec![b'x'; 1024];
+        for i in 0..4096_u32 {
+            db.put(i.to_be_bytes(), &value).unwrap();
+        }
+        db.flush().unwrap();
+
+        for i in 0..4096_u32 {
+            assert_eq!(db.get(i.to_be_bytes()).unwrap().unwrap(), value);
+        }
+    }
+}
+
+#[test]
+fn test_set_report_bg_io_stats() {
+    // Flushes and compactions only log their I/O times when the option is set.
+    fn background_work_log(report_bg_io_stats: bool) -> String {
+        let path = DBPath::new("_rust_rocksdb_test_set_report_bg_io_stats");
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_report_bg_io_stats(report_bg_io_stats);
+        {
+            let db = DB::open(&opts, &path).unwrap();
+            for round in 0..4_u32 {
+                for i in 0..1024_u32 {
+                    db.put(i.to_be_bytes(), round.to_be_bytes()).unwrap();
+                }
+                db.flush().unwrap();
+            }
+            db.compact_range(None::<&[u8]>, None::<&[u8]>);
+        }
+
+        // The LOG file is only flushed for sure once the database is closed.
+        let mut rocksdb_log = fs::File::open(format!("{}/LOG", (&path).as_ref().to_str().unwrap()))
+            .expect("rocksdb creates a LOG file");
+        let mut log = String::new();
+        rocksdb_log
+            .read_to_string(&mut log)
+            .expect("can read the LOG file");
+        assert!(log.contains("\"event\": \"flush_finished\""), "{}", log);
+        assert!(
+            log.contains("\"event\": \"compaction_finished\""),
+            "{}",
+            log
+        );
+        log
+    }
+
+    assert!(background_work_log(true).contains("\"file_write_nanos\""));
+    assert!(!background_work_log(false).contains("\"file_write_nanos\""));
+}
+
+#[test]
+fn test_set_max_compaction_bytes() {
+    let path = DBPath::new("_rust_rocksdb_test_set_max_compaction_bytes");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_compression_type(DBCompressionType::None);
+        opts.set_target_file_size_base(64 << 10);
+        opts.set_max_compaction_bytes(256 << 10);
+        let db = DB::open(&opts, &path).unwrap();
+
+        let value = vec![b'x'; 1024];
+        for round in 0..4_u32 {
+            for i in 0..512_u32 {
+                db.put(i.to_be_bytes(), [&round.to_be_bytes()[..], &value].concat())
+                    .unwrap();
+            }
+            db.flush().unwrap();
+        }
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+        let files = db.live_files().unwrap();
+        assert!(files.iter().all(|f| f.level > 0), "{:?}", files);
+        for i in 0..512_u32 {
+            let v = db.get(i.to_be_bytes()).unwrap().unwrap();
+            assert_eq!(&v[..4], &3_u32.to_be_bytes());
+        }
+    }
+}
+
+#[test]
+fn test_set_target_file_size() {
+    let path = DBPath::new("_rust_rocksdb_test_set_target_file_size");
+    {
+        let target_size = 64 << 10;
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_compression_type(DBCompressionType::None);
+        opts.set_target_file_size_base(target_size);
+        opts.set_target_file_size_multiplier(2);
+        let db = DB::open(&opts, &path).unwrap();
+
+        let value = vec![b'x'; 1024];
+        for i in 0..1024_u32 {
+            db.put(i.to_be_bytes(), &value).unwrap();
+        }
+        db.flush().unwrap();
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+        // Compaction output is cut into files of about the target size of level 1.
+        let files = db.live_files().unwrap();
+        assert!(files.len() > 8, "{:?}", files);
+        for file in &files {
+            assert_eq!(file.level, 1);
+            assert!((file.size as u64) < 2 * target_size, "{:?}", file);
+        }
+    }
+}
+
+#[test]
+fn test_set_level_zero_stop_writes_trigger() {
+    let path = DBPath::new("_rust_rocksdb_test_set_level_zero_stop_writes_trigger");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_level_zero_file_num_compaction_trigger(2);
+        opts.set_level_zero_slowdown_writes_trigger(2);
+        opts.set_level_zero_stop_writes_trigger(2);
+        // Keep the L0 -> L1 compaction busy long enough to observe the stall.
+        opts.set_compaction_filter("slow", |_, _: &[u8], _: &[u8]| {
+            std::thread::sleep(std::time::Duration::from_millis(500));
+            CompactionDecision::Keep
+        });
+        let db = DB::open(&opts, &path).unwrap();
+
+        db.put(b"k1", b"v1").unwrap();
+        db.flush().unwrap();
+        db.put(b"k2", b"v2").unwrap();
+        db.flush().unwrap();
+
+        // Two L0 files reach the stop trigger, so writes which must not wait fail right away.
+        let mut write_opts = WriteOptions::default();
+        write_opts.set_no_slowdown(true);
+        let err = db.put_opt(b"k3", b"v3", &write_opts).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Incomplete);
+
+        // Regular writes wait until the compaction has brought L0 under the trigger.
+        db.put(b"k3", b"v3").unwrap();
+        assert_eq!(db.get(b"k3").unwrap().unwrap(), b"v3");
+    }
+}
+
+#[test]
+fn test_set_max_sequential_skip_in_iterations() {
+    let path = DBPath::new("_rust_rocksdb_test_set_max_sequential_skip_in_iterations");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_max_sequential_skip_in_iterations(4);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // Keep all versions alive in the memtable so that iterators have to skip over them.
+        for version in 0..1000_u32 {
+            db.put(b"a", version.to_be_bytes()).unwrap();
+            db.put(b"b", version.to_be_bytes()).unwrap();
+        }
+        db.put(b"c", b"last").unwrap();
+
+        let items: Vec<_> = db
+            .iterator(IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(&*items[0].1, &999_u32.to_be_bytes());
+        assert_eq!(&*items[1].1, &999_u32.to_be_bytes());
+        assert_eq!(&*items[2].1, b"last");
+
+        let items: Vec<_> = db.iterator(IteratorMode::End).map(Result::unwrap).collect();
+        assert_eq!(items.len(), 3);
+        assert_eq!(&*items[2].0, b"a");
+        assert_eq!(&*items[2].1, &999_u32.to_be_bytes());
+    }
+}
+
+#[test]
+fn test_set_write_buffer_number() {
+    let path = DBPath::new("_rust_rocksdb_test_set_write_buffer_number");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.set_write_buffer_size(64 << 10);
+        opts.set_max_write_buffer_number(4);
+        opts.set_min_write_buffer_number_to_merge(2);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // Four bursts of 256KiB each overwrite the same keys, filling at least 16 memtables.
+        let value = vec![b'x'; 1024];
+        for round in 0..4_u32 {
+            for i in 0..256_u32 {
+                db.put(i.to_be_bytes(), [&round.to_be_bytes()[..], &value].concat())
+                    .unwrap();
+            }
+            let immutable = db
+                .property_int_value(properties::NUM_IMMUTABLE_MEM_TABLE)
+                .unwrap()
+                .unwrap();
+            assert!(immutable < 4, "{}", immutable);
+        }
+        db.flush().unwrap();
+
+        // Background flushes only start once two memtables are full and write them out as one
+        // file, so there are noticeably fewer files than memtables.
+        let files = db
+            .property_int_value(properties::num_files_at_level(0))
+            .unwrap()
+            .unwrap();
+        assert!(files > 0 && files < 12, "{}", files);
+
+        for i in 0..256_u32 {
+            let v = db.get(i.to_be_bytes()).unwrap().unwrap();
+            assert_eq!(&v[..4], &3_u32.to_be_bytes());
+        }
+    }
+}
+
+#[test]
+fn test_set_info_log_rotation() {
+    let n = DBPath::new("_rust_rocksdb_test_set_info_log_rotation");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_max_log_file_size(4096);
+    opts.set_keep_log_file_num(2);
+    opts.set_log_file_time_to_roll(3600);
+    // Each open rolls the info log and logs far more than the maximum size.
+    for i in 0..5_u8 {
+        let db = DB::open(&opts, &n).unwrap();
+        db.put([i], b"value").unwrap();
+    }
+
+    let db = DB::open(&opts, &n).unwrap();
+    for i in 0..5_u8 {
+        assert_eq!(db.get([i]).unwrap().unwrap(), b"value");
+    }
+    let old_logs = fs::read_dir(&n)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("LOG.old"))
+        .count();
+    assert!(old_logs <= 2, "{}", old_logs);
+}
+
+#[test]
+fn test_set_max_bytes_for_level() {
+    let path = DBPath::new("_rust_rocksdb_test_set_max_bytes_for_level");
+    {
+        let base = 64 << 10;
+        let multiplier = 4.0;
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_compression_type(DBCompressionType::None);
+        opts.set_level_compaction_dynamic_level_bytes(false);
+        opts.set_target_file_size_base(16 << 10);
+        opts.set_max_bytes_for_level_base(base);
+        opts.set_max_bytes_for_level_multiplier(multiplier);
+        let db = DB::open(&opts, &path).unwrap();
+
+        let value = vec![b'x'; 1024];
+        for i in 0..2048_u32 {
+            db.put(i.to_be_bytes(), &value).unwrap();
+            if i % 128 == 127 {
+                db.flush().unwrap();
+            }
+        }
+
+        let mut retries = 0;
+        while db
+            .property_int_value(properties::COMPACTION_PENDING)
+            .unwrap()
+            != Some(0)
+            || db
+                .property_int_value(properties::NUM_RUNNING_COMPACTIONS)
+                .unwrap()
+                != Some(0)
+        {
+            retries += 1;
+            assert!(retries < 200, "compactions did not settle");
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        let mut level_sizes = std::collections::BTreeMap::new();
+        for file in db.live_files().unwrap() {
+            *level_sizes.entry(file.level).or_insert(0_u64) += file.size as u64;
+        }
+        let bottom = *level_sizes.keys().max().unwrap();
+        assert!(bottom >= 2, "{:?}", level_sizes);
+
+        // Every level above the bottom one stays within its configured target,
+        // base * multiplier^(level - 1), with some slack for the last output file.
+        for (&level, &size) in level_sizes.range(1..bottom) {
+            let target = base as f64 * multiplier.powi(level - 1);
+            assert!((size as f64) < target * 1.5, "{:?}", level_sizes);
+        }
+    }
+}
+
+#[test]
+fn test_set_hybrid_ribbon_filter() {
+    let path = DBPath::new("_rust_rocksdb_test_set_hybrid_ribbon_filter");
+    {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_hybrid_ribbon_filter(10.0, 1);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.set_block_based_table_factory(&block_opts);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // Even keys end up in the bottommost level (Ribbon filters), odd keys
+        // in level 0 (Bloom filters).
+        for i in (0..200_u32).step_by(2) {
+            db.put(i.to_be_bytes(), i.to_le_bytes()).unwrap();
+        }
+        db.flush().unwrap();
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+        for i in (1..200_u32).step_by(2) {
+            db.put(i.to_be_bytes(), i.to_le_bytes()).unwrap();
+        }
+        db.flush().unwrap();
+
+        let levels: Vec<_> = db.live_files().unwrap().iter().map(|f| f.level).collect();
+        assert!(
+            levels.contains(&0) && levels.iter().any(|&l| l > 0),
+            "{:?}",
+            levels
+        );
+        for i in 0..200_u32 {
+            let value = db.get(i.to_be_bytes()).unwrap().unwrap();
+            assert_eq!(value, i.to_le_bytes());
+        }
+        for i in 200..400_u32 {
+            assert!(db.get(i.to_be_bytes()).unwrap().is_none());
+        }
+    }
+}
@@ -16,7 +16,7 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{Error, Options, SstFileWriter, DB};
+use rocksdb::{Error, IngestExternalFileOptions, Options, SstFileWriter, DB};
 use util::DBPath;
 
 #[test]
@@ -50,3 +50,76 @@ fn sst_file_writer_works() {
         assert!(db.get(b"k3").unwrap().is_none());
     }
 }
+
+#[test]
+fn ingest_external_file_cf_opts() {
+    let db_path = DBPath::new("_rust_rocksdb_ingest_external_file_cf_opts");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_ingest_external_file_cf_opts")
+        .tempdir()
+        .expect("Failed to create temporary path for file writer.");
+    let writer_path = dir.path().join("filewriter");
+    {
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(&writer_path).unwrap();
+        for i in 0..10 {
+            writer.put(format!("k{}", i), format!("sst{}", i)).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &db_path, &["cf1"]).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+    db.put_cf(&cf1, b"k5", b"memtable").unwrap();
+
+    // The file overlaps the unflushed memtable of cf1.
+    let mut ingest_opts = IngestExternalFileOptions::default();
+    ingest_opts.set_move_files(true);
+    ingest_opts.set_allow_blocking_flush(false);
+    assert!(db
+        .ingest_external_file_cf_opts(&cf1, &ingest_opts, vec![&writer_path])
+        .is_err());
+    assert!(writer_path.exists());
+
+    ingest_opts.set_allow_blocking_flush(true);
+    db.ingest_external_file_cf_opts(&cf1, &ingest_opts, vec![&writer_path])
+        .unwrap();
+    assert!(!writer_path.exists());
+
+    for i in 0..10 {
+        let value = db.get_cf(&cf1, format!("k{}", i)).unwrap().unwrap();
+        assert_eq!(value, format!("sst{}", i).as_bytes());
+    }
+    assert!(db.get(b"k0").unwrap().is_none());
+}
+
+#[test]
+fn sst_file_writer_requires_sorted_keys() {
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_sorted_keys")
+        .tempdir()
+        .expect("Failed to create temporary path for file writer.");
+    let writer_path = dir.path().join("filewriter");
+
+    let opts = Options::default();
+    let mut writer = SstFileWriter::create(&opts);
+    writer.open(&writer_path).unwrap();
+    writer.put(b"k1", b"v1").unwrap();
+    writer.merge(b"k2", b"v2").unwrap();
+    writer.delete(b"k3").unwrap();
+    writer.put(b"k4", b"v4").unwrap();
+
+    // Going backwards, or repeating the last key, is rejected.
+    assert!(writer.put(b"k0", b"v0").is_err());
+    assert!(writer.delete(b"k4").is_err());
+
+    writer.put(b"k5", b"v5").unwrap();
+    writer.finish().unwrap();
+    assert!(writer.file_size() > 0);
+    let metadata = std::fs::metadata(&writer_path).unwrap();
+    assert_eq!(metadata.len(), writer.file_size());
+}
@@ -15,12 +15,19 @@
 
 mod util;
 
+use std::{
+    convert::TryInto,
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
 use pretty_assertions::assert_eq;
 
 use rocksdb::{
-    CuckooTableOptions, DBAccess, Direction, Error, ErrorKind, IteratorMode, Options, ReadOptions,
-    SliceTransform, TransactionDB, TransactionDBOptions, TransactionOptions,
-    WriteBatchWithTransaction, WriteOptions, DB,
+    CuckooTableOptions, DBAccess, Direction, Error, ErrorKind, IteratorMode, MergeOperands,
+    Options, PendingWrite, ReadOptions, SliceTransform, TransactionDB, TransactionDBOptions,
+    TransactionOptions, WriteBatch, WriteBatchWithTransaction, WriteOptions, WriteType, DB,
 };
 use util::DBPath;
 
@@ -92,6 +99,40 @@ fn put_get() {
     }
 }
 
+#[test]
+fn put_get_delete_outside_transaction() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_put_get_delete_outside_transaction");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+
+        db.put(b"k1", b"v1").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+        db.put_cf(&cf1, b"k1", b"cf_v1").unwrap();
+        db.put_cf(&cf1, b"k2", b"cf_v2").unwrap();
+        db.delete(b"k2").unwrap();
+        db.delete_cf(&cf1, b"k1").unwrap();
+
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert!(db.get(b"k2").unwrap().is_none());
+        assert!(db.get_cf(&cf1, b"k1").unwrap().is_none());
+        assert_eq!(db.get_cf(&cf1, b"k2").unwrap().unwrap(), b"cf_v2");
+
+        // Writes made directly on the DB are visible to transactions.
+        let txn = db.transaction();
+        assert_eq!(txn.get(b"k1").unwrap().unwrap(), b"v1");
+        assert!(txn.get(b"k2").unwrap().is_none());
+        assert!(txn.get_cf(&cf1, b"k1").unwrap().is_none());
+        assert_eq!(txn.get_cf(&cf1, b"k2").unwrap().unwrap(), b"cf_v2");
+        txn.commit().unwrap();
+    }
+}
+
 #[test]
 fn multi_get() {
     let path = DBPath::new("_rust_rocksdb_multi_get");
@@ -211,6 +252,58 @@ fn multi_get_cf() {
     }
 }
 
+#[test]
+fn transaction_multi_get_pending_writes() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_multi_get_pending_writes");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+        db.put_cf(&cf1, b"k2", b"cf_v2").unwrap();
+
+        let txn = db.transaction();
+        // k0 is written in the transaction, k1 is deleted, and k2 is left untouched.
+        txn.put(b"k0", b"v0").unwrap();
+        txn.delete(b"k1").unwrap();
+        txn.put_cf(&cf1, b"k0", b"cf_v0").unwrap();
+
+        let expected = vec![Some(b"v0".to_vec()), None, Some(b"v2".to_vec())];
+        let values = txn
+            .multi_get([b"k0", b"k1", b"k2"])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+
+        let values = txn
+            .multi_get_opt([b"k0", b"k1", b"k2"], &ReadOptions::default())
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(values, expected);
+
+        let values = txn
+            .multi_get_cf(vec![(&cf1, b"k0"), (&cf1, b"k1"), (&cf1, b"k2")])
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![Some(b"cf_v0".to_vec()), None, Some(b"cf_v2".to_vec())]
+        );
+
+        // The base DB doesn't see the pending writes.
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert!(db.get(b"k0").unwrap().is_none());
+    }
+}
+
 #[test]
 fn destroy_on_open() {
     let path = DBPath::new("_rust_rocksdb_transaction_db_destroy_on_open");
@@ -445,6 +538,117 @@ fn transaction() {
     }
 }
 
+#[test]
+fn transaction_get_for_update_timeout() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_get_for_update_timeout");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+
+        let txn1 = db.transaction();
+        txn1.put(b"k1", b"locked").unwrap();
+
+        // The lock wait is bounded by the lock timeout of the transaction, not by a deadline.
+        let mut txn_opts = TransactionOptions::default();
+        txn_opts.set_lock_timeout(10);
+        let txn2 = db.transaction_opt(&WriteOptions::default(), &txn_opts);
+        let readopts = ReadOptions::default();
+
+        let start = Instant::now();
+        let err = txn2.get_for_update_opt(b"k1", true, &readopts).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        assert_eq!(
+            txn2.get_for_update_opt(b"k2", true, &readopts)
+                .unwrap()
+                .unwrap(),
+            b"v2"
+        );
+
+        txn1.commit().unwrap();
+        assert_eq!(
+            txn2.get_for_update_opt(b"k1", true, &readopts)
+                .unwrap()
+                .unwrap(),
+            b"locked"
+        );
+    }
+}
+
+#[test]
+fn transaction_get_for_update_deadline() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_get_for_update_deadline");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.flush().unwrap();
+        db.put(b"k2", b"v2").unwrap();
+
+        let txn = db.transaction();
+        let mut expired = ReadOptions::default();
+        expired.set_deadline(1);
+
+        // The deadline is checked before reading the SST file holding k1, while k2 is found in
+        // the memtable without any I/O.
+        let err = txn.get_for_update_opt(b"k1", true, &expired).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert_eq!(
+            txn.get_for_update_opt(b"k2", true, &expired)
+                .unwrap()
+                .unwrap(),
+            b"v2"
+        );
+
+        let mut readopts = ReadOptions::default();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        readopts.set_deadline((now + Duration::from_secs(60)).as_micros() as u64);
+        assert_eq!(
+            txn.get_for_update_opt(b"k1", true, &readopts)
+                .unwrap()
+                .unwrap(),
+            b"v1"
+        );
+    }
+}
+
+#[test]
+fn transaction_put_batch() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_put_batch");
+    {
+        let mut txn_db_opts = TransactionDBOptions::default();
+        txn_db_opts.set_txn_lock_timeout(10);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let db: TransactionDB = TransactionDB::open(&opts, &txn_db_opts, &path).unwrap();
+        let pairs = || (0..100).map(|i| (format!("k{}", i), format!("v{}", i)));
+
+        let txn = db.transaction();
+        txn.put_batch(pairs()).unwrap();
+        txn.commit().unwrap();
+        for (k, v) in pairs() {
+            assert_eq!(db.get(k).unwrap().unwrap(), v.as_bytes());
+        }
+
+        // A conflict on the 51st pair stops the batch there.
+        let txn1 = db.transaction();
+        txn1.put(b"k50", b"locked").unwrap();
+        let txn2 = db.transaction();
+        let err = txn2
+            .put_batch(pairs().map(|(k, _)| (k, "new")))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(
+            err.to_string().ends_with(" (after putting 50 pairs)"),
+            "{}",
+            err
+        );
+        assert_eq!(txn2.get(b"k49").unwrap().unwrap(), b"new");
+        assert_eq!(txn2.get(b"k50").unwrap().unwrap(), b"v50");
+        assert_eq!(txn2.get(b"k51").unwrap().unwrap(), b"v51");
+    }
+}
+
 #[test]
 fn transaction_iterator() {
     let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_iterator");
@@ -535,6 +739,192 @@ fn transaction_rollback() {
     }
 }
 
+#[test]
+fn transaction_lock_key() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_lock_key");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut txn_db_opts = TransactionDBOptions::default();
+        txn_db_opts.set_txn_lock_timeout(10);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &txn_db_opts, &path, ["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k1", b"v1").unwrap();
+
+        let txn1 = db.transaction();
+        assert!(txn1.lock_key(b"k1", true).unwrap());
+        assert!(!txn1.lock_key(b"k2", true).unwrap());
+        assert!(!txn1.lock_key_cf(&cf1, b"k1", true).unwrap());
+
+        // The locks conflict with other writers just like get_for_update's.
+        let txn2 = db.transaction();
+        for err in [
+            txn2.put(b"k1", b"v1_new").unwrap_err(),
+            txn2.put(b"k2", b"v2").unwrap_err(),
+            txn2.put_cf(&cf1, b"k1", b"cf_v1").unwrap_err(),
+            db.put(b"k1", b"v1_new").unwrap_err(),
+        ] {
+            assert_eq!(err.kind(), ErrorKind::TimedOut);
+        }
+
+        txn1.commit().unwrap();
+        txn2.put(b"k1", b"v1_new").unwrap();
+        txn2.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1_new");
+    }
+}
+
+#[test]
+fn transaction_lock_keys_ordered() {
+    const N: usize = 100;
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_lock_keys_ordered");
+    {
+        let db: Arc<TransactionDB> = Arc::new(TransactionDB::open_default(&path).unwrap());
+        db.put(b"counter", 0u64.to_le_bytes()).unwrap();
+        let barrier = Arc::new(Barrier::new(2));
+
+        // Both threads lock overlapping key sets, passed in opposite orders.
+        const KEY_SETS: [&[&[u8]]; 2] = [
+            &[b"k1", b"k2", b"counter", b"k3"],
+            &[b"k3", b"counter", b"k2", b"k2", b"k4"],
+        ];
+        let handles: Vec<_> = KEY_SETS
+            .iter()
+            .map(|&keys| {
+                let db = db.clone();
+                let barrier = barrier.clone();
+                thread::spawn(move || {
+                    barrier.wait();
+                    for _ in 0..N {
+                        let txn = db.transaction();
+                        txn.lock_keys_ordered(keys, true).unwrap();
+                        let counter = txn.get(b"counter").unwrap().unwrap();
+                        let counter = u64::from_le_bytes(counter.try_into().unwrap());
+                        for key in keys.iter() {
+                            txn.put(key, (counter + 1).to_le_bytes()).unwrap();
+                        }
+                        txn.commit().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let counter = db.get(b"counter").unwrap().unwrap();
+        assert_eq!(
+            u64::from_le_bytes(counter.try_into().unwrap()),
+            2 * N as u64
+        );
+    }
+}
+
+#[test]
+fn transaction_get_committed() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_get_committed");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put_cf(&cf1, b"k1", b"cf_v1").unwrap();
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1_new").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.put_cf(&cf1, b"k1", b"cf_v1_new").unwrap();
+
+        assert_eq!(txn.get(b"k1").unwrap().unwrap(), b"v1_new");
+        assert_eq!(txn.get_committed(b"k1").unwrap().unwrap(), b"v1");
+        assert!(txn.get_committed(b"k2").unwrap().is_none());
+        assert_eq!(txn.get_cf(&cf1, b"k1").unwrap().unwrap(), b"cf_v1_new");
+        assert_eq!(
+            txn.get_committed_cf(&cf1, b"k1").unwrap().unwrap(),
+            b"cf_v1"
+        );
+
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1_new");
+    }
+}
+
+#[test]
+fn transaction_num_operations() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_num_operations");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        let txn = db.transaction();
+        assert_eq!(txn.num_writes(), Some(0));
+
+        txn.put(b"k1", b"v1").unwrap();
+        txn.put_cf(&cf1, b"k2", [b'v'; 200]).unwrap();
+        txn.put(b"k3", b"v3").unwrap();
+        txn.delete(b"k4").unwrap();
+        txn.delete_cf(&cf1, b"k5").unwrap();
+        txn.merge(b"k6", b"m1").unwrap();
+        txn.merge_cf(&cf1, b"k6", b"m2").unwrap();
+        txn.merge(b"k7", b"m3").unwrap();
+        assert_eq!(txn.num_writes(), Some(3));
+        assert_eq!(txn.num_deletes(), Some(2));
+        assert_eq!(txn.num_merges(), Some(3));
+
+        // Operations rolled back to a save point are not counted anymore.
+        txn.set_savepoint();
+        txn.put(b"k8", b"v8").unwrap();
+        txn.delete(b"k9").unwrap();
+        assert_eq!((txn.num_writes(), txn.num_deletes()), (Some(4), Some(3)));
+        txn.rollback_to_savepoint().unwrap();
+        assert_eq!((txn.num_writes(), txn.num_deletes()), (Some(3), Some(2)));
+    }
+}
+
+#[test]
+fn transaction_num_savepoints() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_num_savepoints");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        let txn = db.transaction();
+        assert_eq!(txn.num_savepoints(), 0);
+
+        txn.set_savepoint();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.set_savepoint();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.set_savepoint();
+        assert_eq!(txn.num_savepoints(), 3);
+
+        txn.rollback_to_savepoint().unwrap();
+        assert_eq!(txn.num_savepoints(), 2);
+        txn.rollback_to_savepoint().unwrap();
+        assert_eq!(txn.num_savepoints(), 1);
+        assert_eq!(txn.get(b"k1").unwrap().unwrap(), b"v1");
+        assert!(txn.get(b"k2").unwrap().is_none());
+
+        // A full rollback removes the remaining save points.
+        txn.set_savepoint();
+        assert_eq!(txn.num_savepoints(), 2);
+        txn.rollback().unwrap();
+        assert_eq!(txn.num_savepoints(), 0);
+
+        // Rolling back without a save point fails and leaves the count alone.
+        assert!(txn.rollback_to_savepoint().is_err());
+        assert_eq!(txn.num_savepoints(), 0);
+    }
+}
+
 #[test]
 fn transaction_cf() {
     let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_cf");
@@ -600,6 +990,45 @@ fn transaction_snapshot() {
     }
 }
 
+#[test]
+fn transaction_name() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_name");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+
+        let txn = db.transaction();
+        assert!(txn.get_name().is_none());
+        txn.set_name(b"txn1").unwrap();
+        assert_eq!(txn.get_name().unwrap(), b"txn1");
+
+        let txn2 = db.transaction();
+        txn2.set_name(String::from("txn2")).unwrap();
+        assert_eq!(txn2.get_name().unwrap(), b"txn2");
+    }
+}
+
+#[test]
+fn two_phase_commit_durable() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_2pc_durable");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+
+        let txn = db.transaction();
+        txn.set_name(b"txn1").unwrap();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.prepare().unwrap();
+        // Prepared writes are not visible until committed.
+        assert!(db.get(b"k1").unwrap().is_none());
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    }
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        assert!(db.prepared_transactions().is_empty());
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    }
+}
+
 #[test]
 fn two_phase_commit() {
     let path = DBPath::new("_rust_rocksdb_transaction_db_2pc");
@@ -618,7 +1047,7 @@ fn two_phase_commit() {
         assert_eq!(err.kind(), ErrorKind::InvalidArgument);
 
         let mut opt = TransactionOptions::new();
-        opt.set_skip_prepare(false);
+        opt.set_snapshot(false);
         let txn = db.transaction_opt(&WriteOptions::default(), &opt);
         txn.put(b"k3", b"v3").unwrap();
         let err = txn.prepare().unwrap_err();
@@ -676,6 +1105,300 @@ fn two_phase_commit() {
     }
 }
 
+#[test]
+fn prepared_transactions_recovery() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_prepared_transactions_recovery");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        let txn = db.transaction();
+        txn.set_name(b"txn1").unwrap();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.prepare().unwrap();
+        // The DB is closed without resolving the prepared transaction.
+    }
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        let mut txns = db.prepared_transactions();
+        assert_eq!(txns.len(), 1);
+        // The prepared transactions are handed out only once.
+        assert!(db.prepared_transactions().is_empty());
+
+        let txn = txns.pop().unwrap();
+        assert_eq!(txn.get_name().unwrap(), b"txn1");
+        assert_eq!(txn.get(b"k1").unwrap().unwrap(), b"v1");
+        assert!(db.get(b"k1").unwrap().is_none());
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    }
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        assert!(db.prepared_transactions().is_empty());
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    }
+}
+
+#[test]
+fn transaction_get_pinned() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_get_pinned");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k1", b"v1").unwrap();
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1_new").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.put_cf(&cf1, b"k1", b"cf_v1").unwrap();
+
+        assert_eq!(txn.get_pinned(b"k1").unwrap().unwrap().as_ref(), b"v1_new");
+        assert_eq!(
+            txn.get_pinned_opt(b"k2", &ReadOptions::default())
+                .unwrap()
+                .unwrap()
+                .as_ref(),
+            b"v2"
+        );
+        assert!(txn.get_pinned(b"k3").unwrap().is_none());
+        assert_eq!(
+            txn.get_pinned_cf(&cf1, b"k1").unwrap().unwrap().as_ref(),
+            b"cf_v1"
+        );
+        assert!(txn
+            .get_pinned_cf_opt(&cf1, b"k2", &ReadOptions::default())
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[derive(Default)]
+struct RecordedOps {
+    puts: Vec<(Vec<u8>, Vec<u8>)>,
+    deletes: Vec<Vec<u8>>,
+}
+
+impl rocksdb::WriteBatchIterator for RecordedOps {
+    fn put(&mut self, key: Box<[u8]>, value: Box<[u8]>) {
+        self.puts.push((key.into_vec(), value.into_vec()));
+    }
+    fn delete(&mut self, key: Box<[u8]>) {
+        self.deletes.push(key.into_vec());
+    }
+}
+
+#[test]
+fn transaction_get_writebatch() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_get_writebatch");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        db.put(b"k3", b"v3").unwrap();
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.delete(b"k3").unwrap();
+
+        let batch = txn.get_writebatch();
+        assert_eq!(batch.len(), 3);
+        let mut ops = RecordedOps::default();
+        batch.iterate(&mut ops);
+        assert_eq!(
+            ops.puts,
+            vec![
+                (b"k1".to_vec(), b"v1".to_vec()),
+                (b"k2".to_vec(), b"v2".to_vec())
+            ]
+        );
+        assert_eq!(ops.deletes, vec![b"k3".to_vec()]);
+
+        // The copy doesn't follow later writes, and nothing is committed yet.
+        txn.put(b"k4", b"v4").unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(txn.get_writebatch().len(), 4);
+        assert_eq!(db.get(b"k3").unwrap().unwrap(), b"v3");
+    }
+}
+
+#[test]
+fn transaction_get_writebatch_savepoint() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_get_writebatch_savepoint");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.set_savepoint();
+        txn.put(b"k3", b"v3").unwrap();
+        txn.delete(b"k1").unwrap();
+        txn.rollback_to_savepoint().unwrap();
+
+        // Writes rolled back to the save point are gone from the batch.
+        let mut ops = RecordedOps::default();
+        txn.get_writebatch().iterate(&mut ops);
+        assert_eq!(
+            ops.puts,
+            vec![
+                (b"k1".to_vec(), b"v1".to_vec()),
+                (b"k2".to_vec(), b"v2".to_vec())
+            ]
+        );
+        assert!(ops.deletes.is_empty());
+    }
+}
+
+fn concat_merge(_key: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    let mut value = existing.unwrap_or_default().to_vec();
+    for operand in operands {
+        value.extend_from_slice(operand);
+    }
+    Some(value)
+}
+
+#[test]
+fn transaction_pending_iterator() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_pending_iterator");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        opts.set_merge_operator_associative("concat", concat_merge);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &TransactionDBOptions::default(), &path, ["cf1"])
+                .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        db.put(b"k0", b"v0").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.delete(b"k2").unwrap();
+        txn.merge(b"k3", b"m3").unwrap();
+        txn.put_cf(&cf1, b"k4", b"v4").unwrap();
+
+        // Keys only present in the DB are not part of the pending writes.
+        let pending: Vec<_> = txn.pending_iterator().unwrap().collect();
+        let write = |write_type, cf_id, key: &[u8], value: Option<&[u8]>| PendingWrite {
+            write_type,
+            cf_id,
+            key: Box::from(key),
+            value: value.map(Box::from),
+        };
+        assert_eq!(
+            pending,
+            vec![
+                write(WriteType::Put, 0, b"k1", Some(&b"v1"[..])),
+                write(WriteType::Delete, 0, b"k2", None),
+                write(WriteType::Merge, 0, b"k3", Some(&b"m3"[..])),
+                write(WriteType::Put, 1, b"k4", Some(&b"v4"[..])),
+            ]
+        );
+        assert_eq!(txn.iterator(IteratorMode::Start).count(), 3);
+    }
+}
+
+#[test]
+fn transaction_to_bytes_round_trip() {
+    let leader_path = DBPath::new("_rust_rocksdb_transaction_db_to_bytes_leader");
+    let follower_path = DBPath::new("_rust_rocksdb_transaction_db_to_bytes_follower");
+    {
+        let leader: TransactionDB = TransactionDB::open_default(&leader_path).unwrap();
+        let follower = DB::open_default(&follower_path).unwrap();
+        follower.put(b"k3", b"v3").unwrap();
+
+        let txn = leader.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.delete(b"k3").unwrap();
+        let bytes = txn.to_bytes();
+        txn.commit().unwrap();
+
+        let batch = WriteBatch::from_data(&bytes);
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch.data(), &bytes[..]);
+        follower.write(batch).unwrap();
+
+        assert_eq!(follower.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(follower.get(b"k2").unwrap().unwrap(), b"v2");
+        assert!(follower.get(b"k3").unwrap().is_none());
+    }
+}
+
+#[test]
+fn transaction_commit_sync() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_commit_sync");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        let mut write_opts = WriteOptions::default();
+        write_opts.set_sync(true);
+        let txn = db.transaction_opt(&write_opts, &TransactionOptions::default());
+        txn.put(b"k1", b"v1").unwrap();
+        txn.commit().unwrap();
+
+        // Other transactions keep the default, asynchronous writes.
+        let txn = db.transaction();
+        txn.put(b"k2", b"v2").unwrap();
+        txn.commit().unwrap();
+    }
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get(b"k2").unwrap().unwrap(), b"v2");
+    }
+}
+
+#[test]
+fn transaction_reuse() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_reuse");
+    {
+        let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+
+        // Pending writes, savepoints and locks of the old transaction are discarded.
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.set_savepoint();
+        txn.put(b"k2", b"v2").unwrap();
+        let txn = db.reuse_transaction(txn).unwrap();
+        assert_eq!(txn.num_savepoints(), 0);
+        assert_eq!(txn.get_writebatch().len(), 0);
+        assert!(txn.rollback_to_savepoint().is_err());
+        db.put(b"k1", b"v0").unwrap();
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v0");
+        assert!(db.get(b"k2").unwrap().is_none());
+
+        let mut txn = db.transaction();
+        for i in 0..10_000u32 {
+            txn.put(b"counter", i.to_le_bytes()).unwrap();
+            txn.commit().unwrap();
+            txn = db.reuse_transaction(txn).unwrap();
+        }
+        assert_eq!(db.get(b"counter").unwrap().unwrap(), 9_999u32.to_le_bytes());
+    }
+}
+
+#[test]
+fn transaction_reuse_other_db() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_reuse_other_db");
+    let other_path = DBPath::new("_rust_rocksdb_transaction_db_transaction_reuse_other_db2");
+    let other: TransactionDB = TransactionDB::open_default(&other_path).unwrap();
+    let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+
+    let txn = other.transaction();
+    txn.put(b"k1", b"v1").unwrap();
+    let err = db.reuse_transaction(txn).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // The rejected transaction was dropped, releasing its lock on the other database.
+    let txn = other.transaction();
+    txn.put(b"k1", b"v2").unwrap();
+    txn.commit().unwrap();
+    assert_eq!(other.get(b"k1").unwrap().unwrap(), b"v2");
+}
+
 #[test]
 fn test_snapshot_outlive_transaction_db() {
     let t = trybuild::TestCases::new();
@@ -693,3 +1416,9 @@ fn test_snapshot_outlive_txn() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/fail/snapshot_outlive_transaction.rs");
 }
+
+#[test]
+fn test_pinnable_slice_outlive_txn() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/fail/pinnable_slice_outlive_transaction.rs");
+}
@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod util;
+
 use pretty_assertions::assert_eq;
 
-use rocksdb::WriteBatch;
+use rocksdb::{BatchWriter, IteratorMode, WriteBatch, DB};
+use util::DBPath;
 
 #[test]
 fn test_write_batch_clear() {
@@ -25,3 +28,55 @@ fn test_write_batch_clear() {
     assert_eq!(batch.len(), 0);
     assert!(batch.is_empty());
 }
+
+#[test]
+fn test_batch_writer() {
+    const N: u32 = 1_000_000;
+    let path = DBPath::new("_rust_rocksdb_test_batch_writer");
+    let db = DB::open_default(&path).unwrap();
+
+    let mut writer = BatchWriter::new(&db, 64 << 10, 1_000);
+    for i in 0..N {
+        writer.put(i.to_be_bytes(), i.to_le_bytes()).unwrap();
+    }
+    // Full batches have been written already, the remainder is still buffered.
+    assert!(db.get(0_u32.to_be_bytes()).unwrap().is_some());
+    writer.finish().unwrap();
+
+    let mut count = 0;
+    for (i, item) in db.iterator(IteratorMode::Start).enumerate() {
+        let (key, value) = item.unwrap();
+        let i = i as u32;
+        assert_eq!(&*key, &i.to_be_bytes());
+        assert_eq!(&*value, &i.to_le_bytes());
+        count += 1;
+    }
+    assert_eq!(count, N);
+}
+
+#[test]
+fn test_write_batch_append() {
+    let path = DBPath::new("_rust_rocksdb_test_write_batch_append");
+    let db = DB::open_default(&path).unwrap();
+
+    let mut batch = WriteBatch::default();
+    batch.put(b"k1", b"a");
+    batch.put(b"k2", b"a");
+    batch.put(b"k3", b"a");
+
+    let mut other = WriteBatch::default();
+    other.put(b"k1", b"b");
+    other.delete(b"k2");
+    other.put(b"k4", b"b");
+
+    batch.append(&other);
+    batch.append(&WriteBatch::default());
+    assert_eq!(batch.len(), 6);
+    assert_eq!(other.len(), 3);
+
+    db.write(batch).unwrap();
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"b");
+    assert!(db.get(b"k2").unwrap().is_none());
+    assert_eq!(db.get(b"k3").unwrap().unwrap(), b"a");
+    assert_eq!(db.get(b"k4").unwrap().unwrap(), b"b");
+}